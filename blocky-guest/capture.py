@@ -0,0 +1,77 @@
+# mitmproxy addon that records the exchanges of `bky-as attest-fn-call` with
+# the Blocky service, so the client in styks-cli can be checked against them.
+# Run it with `make capture`.
+#
+# The auth token, the secrets and the guest program are redacted, everything
+# else is kept as sent.
+
+import json
+import os
+
+from mitmproxy import ctx, http
+
+REDACTED = "<redacted>"
+# Longer strings, like the base64 encoded guest program, are shortened.
+MAX_STRING = 512
+
+
+def redact(value, key=""):
+    if isinstance(value, dict):
+        return {k: redact(v, k) for k, v in value.items()}
+    if isinstance(value, list):
+        return [redact(v, key) for v in value]
+    if "secret" in key.lower():
+        return REDACTED
+    if isinstance(value, str) and len(value) > MAX_STRING:
+        return f"<{len(value)} chars>"
+    return value
+
+
+def body(message):
+    text = message.get_text(strict=False) or ""
+    try:
+        return json.loads(text)
+    except ValueError:
+        return text
+
+
+def headers(message):
+    result = {}
+    for name, value in message.headers.items():
+        if name.lower() == "authorization":
+            value = value.split(" ")[0] + " " + REDACTED
+        result[name.lower()] = value
+    return result
+
+
+class Capture:
+    def __init__(self):
+        self.exchanges = []
+
+    def load(self, loader):
+        loader.add_option("capture_out", str, "blocky_exchange.json", "Recording path.")
+
+    def response(self, flow: http.HTTPFlow):
+        self.exchanges.append(
+            {
+                "request": {
+                    "method": flow.request.method,
+                    "path": flow.request.path,
+                    "headers": headers(flow.request),
+                    "body": redact(body(flow.request)),
+                },
+                "response": {
+                    "status": flow.response.status_code,
+                    "headers": headers(flow.response),
+                    # The attestation is public, it's kept as is.
+                    "body": body(flow.response),
+                },
+            }
+        )
+        path = ctx.options.capture_out
+        os.makedirs(os.path.dirname(path) or ".", exist_ok=True)
+        with open(path, "w") as file:
+            json.dump(self.exchanges, file, indent=2)
+
+
+addons = [Capture()]
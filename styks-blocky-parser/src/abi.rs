@@ -0,0 +1,229 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+#[cfg(not(any(feature = "ethabi", feature = "lean-abi")))]
+compile_error!("Either the `ethabi` or the `lean-abi` feature must be enabled.");
+
+const WORD: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AbiError {
+    OutOfBounds,
+    ValueTooLarge,
+    InvalidType,
+}
+
+// Decodes `abi.encode(bytes[])`, the shape of both the TA and its data.
+// Uses the lean decoder when the `lean-abi` feature is enabled, so contracts
+// don't need to link `ethabi`.
+pub fn decode_bytes_array_owned(bytes: &[u8]) -> Result<Vec<Vec<u8>>, AbiError> {
+    #[cfg(feature = "lean-abi")]
+    {
+        let elements = decode_bytes_array(bytes)?;
+        Ok(elements.into_iter().map(<[u8]>::to_vec).collect())
+    }
+
+    #[cfg(not(feature = "lean-abi"))]
+    {
+        decode_bytes_array_ethabi(bytes)
+    }
+}
+
+// Lean `bytes[]` decoder. The elements borrow from the input, the only
+// allocation is the vector of slices.
+#[cfg(feature = "lean-abi")]
+pub fn decode_bytes_array(bytes: &[u8]) -> Result<Vec<&[u8]>, AbiError> {
+    // Head: offset of the array.
+    let array_offset = read_usize(bytes, 0)?;
+
+    // Array: length followed by the offsets of the elements, relative to the
+    // first offset.
+    let count = read_usize(bytes, array_offset)?;
+    let head = checked_add(array_offset, WORD)?;
+
+    // Every element needs at least its offset, don't allocate for more.
+    let available = bytes.len().checked_sub(head).ok_or(AbiError::OutOfBounds)?;
+    if count > available / WORD {
+        return Err(AbiError::OutOfBounds);
+    }
+
+    let mut elements = Vec::with_capacity(count);
+    for i in 0..count {
+        let offset = read_usize(bytes, head + i * WORD)?;
+        let start = checked_add(head, offset)?;
+        let len = read_usize(bytes, start)?;
+        let data_start = checked_add(start, WORD)?;
+        let data_end = checked_add(data_start, len)?;
+        let data = bytes.get(data_start..data_end).ok_or(AbiError::OutOfBounds)?;
+        elements.push(data);
+    }
+    Ok(elements)
+}
+
+#[cfg(feature = "ethabi")]
+pub fn decode_bytes_array_ethabi(bytes: &[u8]) -> Result<Vec<Vec<u8>>, AbiError> {
+    #[cfg(not(feature = "std"))]
+    use alloc::boxed::Box;
+    use ethabi::{decode, ParamType};
+
+    let array = decode(&[ParamType::Array(Box::new(ParamType::Bytes))], bytes)
+        .map_err(|_| AbiError::OutOfBounds)?
+        .pop()
+        .and_then(|token| token.into_array())
+        .ok_or(AbiError::InvalidType)?;
+    array
+        .into_iter()
+        .map(|token| token.into_bytes().ok_or(AbiError::InvalidType))
+        .collect()
+}
+
+// Encodes the elements as `abi.encode(bytes[])`.
+pub fn encode_bytes_array(elements: &[&[u8]]) -> Vec<u8> {
+    fn push_word(out: &mut Vec<u8>, value: usize) {
+        let mut word = [0u8; WORD];
+        word[WORD - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+        out.extend_from_slice(&word);
+    }
+
+    let mut out = Vec::new();
+    push_word(&mut out, WORD);
+    push_word(&mut out, elements.len());
+
+    // Offsets are relative to the first offset.
+    let mut offset = elements.len() * WORD;
+    for element in elements {
+        push_word(&mut out, offset);
+        offset += WORD + element.len().div_ceil(WORD) * WORD;
+    }
+
+    // Length prefixed elements, padded to the full word.
+    for element in elements {
+        push_word(&mut out, element.len());
+        out.extend_from_slice(element);
+        out.resize(out.len() + (WORD - element.len() % WORD) % WORD, 0);
+    }
+    out
+}
+
+#[cfg(feature = "lean-abi")]
+fn checked_add(a: usize, b: usize) -> Result<usize, AbiError> {
+    a.checked_add(b).ok_or(AbiError::OutOfBounds)
+}
+
+// Reads a big-endian uint256 word that has to fit into usize.
+#[cfg(feature = "lean-abi")]
+fn read_usize(bytes: &[u8], at: usize) -> Result<usize, AbiError> {
+    let word = bytes
+        .get(at..checked_add(at, WORD)?)
+        .ok_or(AbiError::OutOfBounds)?;
+    let (high, low) = word.split_at(WORD - 8);
+    if high.iter().any(|byte| *byte != 0) {
+        return Err(AbiError::ValueTooLarge);
+    }
+    let mut value = [0u8; 8];
+    value.copy_from_slice(low);
+    usize::try_from(u64::from_be_bytes(value)).map_err(|_| AbiError::ValueTooLarge)
+}
+
+#[cfg(all(test, feature = "lean-abi", feature = "ethabi"))]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use ethabi::{encode, Token};
+
+    use crate::{blocky_output::BlockyOutput, TestRng};
+
+    use super::*;
+
+    fn ethabi_encode(elements: &[Vec<u8>]) -> Vec<u8> {
+        let tokens = elements.iter().cloned().map(Token::Bytes).collect();
+        encode(&[Token::Array(tokens)])
+    }
+
+    fn assert_same_as_ethabi(bytes: &[u8]) {
+        let lean = decode_bytes_array(bytes).map(|elements| {
+            elements.into_iter().map(<[u8]>::to_vec).collect::<Vec<_>>()
+        });
+        match (lean, decode_bytes_array_ethabi(bytes)) {
+            (Ok(lean), Ok(ethabi)) => assert_eq!(lean, ethabi),
+            // Whatever ethabi accepts, the lean decoder accepts too.
+            (Err(_), Ok(ethabi)) => panic!("Lean decoder rejected {:?}", ethabi),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_fixtures() {
+        for name in ["1_out.json", "2_out.json", "btc_balance_1_out.json", "btc_balance_2_out.json"] {
+            let path = format!("{}/../resources/test/{}", env!("CARGO_MANIFEST_DIR"), name);
+            let output = BlockyOutput::try_from_file(path).unwrap();
+            let ta = &output.transitive_attested_function_call.transitive_attestation;
+            let ta = BASE64_STANDARD.decode(ta).unwrap();
+
+            // TA is [data, signature] and data is the 5 claims.
+            let elements = decode_bytes_array(&ta).unwrap();
+            assert_eq!(elements.len(), 2);
+            let claims = decode_bytes_array(elements[0]).unwrap();
+            assert_eq!(claims.len(), 5);
+
+            assert_same_as_ethabi(&ta);
+            assert_same_as_ethabi(elements[0]);
+        }
+    }
+
+    #[test]
+    fn test_random_round_trips() {
+        let mut rng = TestRng(0xab1);
+        for _ in 0..500 {
+            let elements: Vec<Vec<u8>> = (0..rng.below(8))
+                .map(|_| (0..rng.below(100)).map(|_| rng.next() as u8).collect())
+                .collect();
+            let encoded = ethabi_encode(&elements);
+            let slices: Vec<&[u8]> = elements.iter().map(Vec::as_slice).collect();
+            assert_eq!(encode_bytes_array(&slices), encoded);
+            let decoded = decode_bytes_array(&encoded).unwrap();
+            assert_eq!(decoded, slices);
+            assert_same_as_ethabi(&encoded);
+        }
+    }
+
+    #[test]
+    fn test_random_mutations() {
+        let mut rng = TestRng(0xab2);
+        for _ in 0..2000 {
+            let elements: Vec<Vec<u8>> = (0..1 + rng.below(5))
+                .map(|_| (0..rng.below(70)).map(|_| rng.next() as u8).collect())
+                .collect();
+            let mut encoded = ethabi_encode(&elements);
+            let index = rng.below(encoded.len());
+            encoded[index] = rng.next() as u8;
+            if rng.below(4) == 0 {
+                encoded.truncate(rng.below(encoded.len()));
+            }
+            // Must never panic.
+            assert_same_as_ethabi(&encoded);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(decode_bytes_array(&[]), Err(AbiError::OutOfBounds));
+        assert_eq!(decode_bytes_array(&[0u8; 31]), Err(AbiError::OutOfBounds));
+
+        // Huge offset.
+        let mut bytes = ethabi_encode(&[vec![1, 2, 3]]);
+        bytes[0] = 1;
+        assert_eq!(decode_bytes_array(&bytes), Err(AbiError::ValueTooLarge));
+
+        // Huge count.
+        let mut bytes = ethabi_encode(&[vec![1, 2, 3]]);
+        bytes[63] = 0xff;
+        assert_eq!(decode_bytes_array(&bytes), Err(AbiError::OutOfBounds));
+
+        // Truncated element.
+        let bytes = ethabi_encode(&[vec![7u8; 40]]);
+        assert_eq!(decode_bytes_array(&bytes[..130]), Err(AbiError::OutOfBounds));
+
+        // Empty array.
+        assert_eq!(decode_bytes_array(&ethabi_encode(&[])), Ok(vec![]));
+    }
+}
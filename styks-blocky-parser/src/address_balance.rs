@@ -0,0 +1,95 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
+use serde::Deserialize;
+
+// Output value of the address balance guest program:
+// `{ "chain": "bitcoin", "address": "...", "balance": ..., "block_height": ..., "timestamp": ... }`.
+// The balance is expressed in satoshis. A `u64` holds any Bitcoin balance, but
+// not wei-scale ones, so consumers only accept the bitcoin chain.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AddressBalanceOutputValue {
+    pub chain: String,
+    pub address: String,
+    pub balance: u64,
+    pub block_height: u64,
+    pub timestamp: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        blocky_claims::BlockyClaims,
+        blocky_output::BlockyOutput,
+        test_utils::{TestAttestation, TestSigner},
+        verify::verify_signature,
+    };
+
+    use super::*;
+
+    fn load(name: &str) -> BlockyOutput {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let path = std::path::Path::new(manifest_dir).join("../resources/test").join(name);
+        BlockyOutput::try_from_file(path).expect("Failed to load BlockyOutput")
+    }
+
+    #[test]
+    fn test_decode_recorded_balance() {
+        let output = load("btc_balance_1_out.json");
+        let ta = output.ta().unwrap();
+
+        // The fixture is signed with its enclave key.
+        let public_key = output.public_key_bytes().unwrap();
+        assert!(verify_signature(&public_key, &ta.signature_bytes(), ta.data()).is_ok());
+
+        let claims = BlockyClaims::decode_fn_call_claims(ta.data())
+            .expect("Failed to decode function call claims");
+        assert_eq!(claims.function(), "balanceFunc");
+        assert_eq!(
+            claims.hash_of_code(),
+            output.transitive_attested_function_call.claims.hash_of_code
+        );
+
+        let value: AddressBalanceOutputValue = claims.output_as().expect("Failed to get output");
+        assert_eq!(
+            value,
+            AddressBalanceOutputValue {
+                chain: String::from("bitcoin"),
+                address: String::from("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"),
+                balance: 150_000_000,
+                block_height: 850_000,
+                timestamp: 1760000000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_generated_balance() {
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let attestation = TestAttestation::balance("bitcoin", address, 150_000_000, 850_000, 1760000000);
+        let signer = TestSigner::from_seed(3);
+        let output = attestation.blocky_output(&signer);
+        let ta = output.ta().unwrap();
+
+        // The output is signed with its enclave key.
+        let public_key = output.public_key_bytes().unwrap();
+        assert!(verify_signature(&public_key, &ta.signature_bytes(), ta.data()).is_ok());
+
+        let claims = BlockyClaims::decode_fn_call_claims(ta.data())
+            .expect("Failed to decode function call claims");
+        assert_eq!(claims.function(), "balanceFunc");
+        assert_eq!(claims.hash_of_code(), attestation.hash_of_code);
+
+        let value: AddressBalanceOutputValue = claims.output_as().expect("Failed to get output");
+        assert_eq!(
+            value,
+            AddressBalanceOutputValue {
+                chain: String::from("bitcoin"),
+                address: String::from(address),
+                balance: 150_000_000,
+                block_height: 850_000,
+                timestamp: 1760000000,
+            }
+        );
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttestedDataError {
+    InvalidHex,
+    InvalidU256,
+}
+
+// Output value of a generic data guest program:
+// `{ "key": "...", "type": "bytes" | "u256" | "string", "value": "...", "timestamp": ... }`.
+// Bytes are hex encoded and u256 values are decimal strings.
+#[derive(Deserialize)]
+pub struct AttestedDataOutputValue {
+    pub key: String,
+    #[serde(rename = "type")]
+    pub value_type: AttestedValueType,
+    pub value: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttestedValueType {
+    Bytes,
+    U256,
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttestedValue {
+    Bytes(Vec<u8>),
+    // Decimal representation, guaranteed to fit into 256 bits.
+    U256(String),
+    String(String),
+}
+
+impl AttestedDataOutputValue {
+    pub fn parsed_value(&self) -> Result<AttestedValue, AttestedDataError> {
+        match self.value_type {
+            AttestedValueType::Bytes => decode_hex(&self.value).map(AttestedValue::Bytes),
+            AttestedValueType::U256 => {
+                validate_u256(&self.value)?;
+                Ok(AttestedValue::U256(self.value.clone()))
+            }
+            AttestedValueType::String => Ok(AttestedValue::String(self.value.clone())),
+        }
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, AttestedDataError> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(value).map_err(|_| AttestedDataError::InvalidHex)
+}
+
+// Checks the value is a decimal number lower than 2^256.
+fn validate_u256(value: &str) -> Result<(), AttestedDataError> {
+    const U256_MAX: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+    if value.is_empty() || !value.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(AttestedDataError::InvalidU256);
+    }
+    let digits = value.trim_start_matches('0');
+    let too_big = digits.len() > U256_MAX.len()
+        || (digits.len() == U256_MAX.len() && digits > U256_MAX);
+    if too_big {
+        return Err(AttestedDataError::InvalidU256);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocky_claims::GuestProgramOutput;
+
+    use super::*;
+
+    fn parse(json: &str) -> AttestedDataOutputValue {
+        GuestProgramOutput::<AttestedDataOutputValue>::try_from_string(json)
+            .and_then(GuestProgramOutput::into_result)
+            .expect("Failed to parse output")
+    }
+
+    #[test]
+    fn test_parse_attested_data() {
+        let value = parse(r#"{"success":true,"error":"","value":{"key":"answer","type":"string","value":"42","timestamp":100}}"#);
+        assert_eq!(value.key, "answer");
+        assert_eq!(value.timestamp, 100);
+        assert_eq!(value.parsed_value(), Ok(AttestedValue::String(String::from("42"))));
+
+        let value = parse(r#"{"success":true,"error":"","value":{"key":"hash","type":"bytes","value":"0x00ffA1","timestamp":100}}"#);
+        assert_eq!(value.parsed_value(), Ok(AttestedValue::Bytes(vec![0x00, 0xff, 0xa1])));
+
+        let value = parse(r#"{"success":true,"error":"","value":{"key":"supply","type":"u256","value":"1000","timestamp":100}}"#);
+        assert_eq!(value.parsed_value(), Ok(AttestedValue::U256(String::from("1000"))));
+
+        // Unknown types are rejected.
+        let result = GuestProgramOutput::<AttestedDataOutputValue>::try_from_string(
+            r#"{"success":true,"error":"","value":{"key":"x","type":"u8","value":"1","timestamp":100}}"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert_eq!(decode_hex("abc"), Err(AttestedDataError::InvalidHex));
+        assert_eq!(decode_hex("zz"), Err(AttestedDataError::InvalidHex));
+        assert_eq!(decode_hex(""), Ok(vec![]));
+
+        assert_eq!(validate_u256(""), Err(AttestedDataError::InvalidU256));
+        assert_eq!(validate_u256("-1"), Err(AttestedDataError::InvalidU256));
+        assert_eq!(validate_u256("1.5"), Err(AttestedDataError::InvalidU256));
+        assert_eq!(
+            validate_u256("115792089237316195423570985008687907853269984665640564039457584007913129639935"),
+            Ok(())
+        );
+        assert_eq!(
+            validate_u256("115792089237316195423570985008687907853269984665640564039457584007913129639936"),
+            Err(AttestedDataError::InvalidU256)
+        );
+        assert_eq!(validate_u256("000001"), Ok(()));
+    }
+}
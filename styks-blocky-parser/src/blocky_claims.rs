@@ -1,8 +1,9 @@
 #[cfg(not(feature = "std"))]
-use alloc::{format, string::{String, ToString}, vec::Vec, boxed::Box};
+use alloc::{format, string::{String, ToString}, vec::Vec};
 
-use ethabi::{decode, ParamType, Token};
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::abi::decode_bytes_array_owned;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlockyClaimsError {
@@ -10,9 +11,11 @@ pub enum BlockyClaimsError {
     TADataInvalidLength,
     BytesConversionError,
     OutputJsonDecoding,
-    OutputHasNoSuccessStatus,
+    // The guest program reported a failure, with its error message.
+    GuestProgramFailed(String),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct BlockyClaims {
     pub hash_of_code: Vec<u8>,
     pub function: Vec<u8>,
@@ -23,29 +26,20 @@ pub struct BlockyClaims {
 
 impl BlockyClaims {
     pub fn decode_fn_call_claims(bytes: &[u8]) -> Result<BlockyClaims, BlockyClaimsError> {
-        let decoded = decode(&[ParamType::Array(Box::new(ParamType::Bytes))], bytes)
-            .map_err(|_| BlockyClaimsError::TADataDecoding)?
-            .pop()
-            .and_then(|t| t.into_array())
-            .ok_or(BlockyClaimsError::TADataDecoding)?;
-
-        if decoded.len() != 5 {
-            return Err(BlockyClaimsError::TADataInvalidLength);
-        }
+        let decoded = decode_bytes_array_owned(bytes)
+            .map_err(|_| BlockyClaimsError::TADataDecoding)?;
 
-        fn extract(data: &Token) -> Result<Vec<u8>, BlockyClaimsError> {
-            data
-                .clone()
-                .into_bytes()
-                .ok_or(BlockyClaimsError::BytesConversionError)
-        }
+        let [hash_of_code, function, hash_of_input, output, hash_of_secrets]: [Vec<u8>; 5] =
+            decoded
+                .try_into()
+                .map_err(|_| BlockyClaimsError::TADataInvalidLength)?;
 
         let claims = BlockyClaims {
-            hash_of_code: extract(&decoded[0])?,
-            function: extract(&decoded[1])?,
-            hash_of_input: extract(&decoded[2])?,
-            output: extract(&decoded[3])?,
-            hash_of_secrets: extract(&decoded[4])?,
+            hash_of_code,
+            function,
+            hash_of_input,
+            output,
+            hash_of_secrets,
         };
 
         Ok(claims)
@@ -64,32 +58,76 @@ impl BlockyClaims {
     }
 
     pub fn output(&self) -> Result<GuestProgramOutputValue, BlockyClaimsError> {
-        let output = GuestProgramOutput::try_from_string(&self.output_str())?;
-            
-        if !output.success {
-            return Err(BlockyClaimsError::OutputHasNoSuccessStatus);
-        }
-        Ok(output.value)
+        self.output_as()
+    }
+
+    // Decodes the output of any guest program that follows the
+    // `{ success, error, value }` schema.
+    pub fn output_as<T: DeserializeOwned>(&self) -> Result<T, BlockyClaimsError> {
+        GuestProgramOutput::<T>::try_from_string(&self.output_str())?.into_result()
     }
 
 }
 
+// Output of the guest program. On success it is
+// `{ "success": true, "error": "", "value": ... }`, while failures are written
+// by `blocky-guest/output.go` as `{ "Success": false, "Error": "...", "Value": null }`.
+pub struct GuestProgramOutput<T = GuestProgramOutputValue> {
+    success: bool,
+    error: String,
+    value: Option<T>,
+}
+
+// Status of the output, the value is ignored.
 #[derive(Deserialize)]
-pub struct GuestProgramOutput {
+struct GuestProgramStatus {
+    #[serde(alias = "Success")]
     success: bool,
+    #[serde(alias = "Error", default)]
     error: String,
-    value: GuestProgramOutputValue,
 }
 
-impl GuestProgramOutput {
+#[derive(Deserialize)]
+struct GuestProgramValue<T> {
+    #[serde(alias = "Value")]
+    value: T,
+}
+
+impl<T: DeserializeOwned> GuestProgramOutput<T> {
     pub fn try_from_string(s: &str) -> Result<Self, BlockyClaimsError> {
-        serde_json_wasm::from_str(s)
-            .map_err(|_| BlockyClaimsError::OutputJsonDecoding)
+        let status: GuestProgramStatus = serde_json_wasm::from_str(s)
+            .map_err(|_| BlockyClaimsError::OutputJsonDecoding)?;
+
+        // Decode the value only on success, failures may not have one.
+        let value = if status.success {
+            let value: GuestProgramValue<T> = serde_json_wasm::from_str(s)
+                .map_err(|_| BlockyClaimsError::OutputJsonDecoding)?;
+            Some(value.value)
+        } else {
+            None
+        };
+
+        Ok(GuestProgramOutput {
+            success: status.success,
+            error: status.error,
+            value,
+        })
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.success
     }
 
     pub fn error_message(&self) -> &str {
         &self.error
     }
+
+    pub fn into_result(self) -> Result<T, BlockyClaimsError> {
+        match self.value {
+            Some(value) if self.success => Ok(value),
+            _ => Err(BlockyClaimsError::GuestProgramFailed(self.error)),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -101,12 +139,6 @@ pub struct GuestProgramOutputValue {
     pub timestamp: u64,
 }
 
-impl GuestProgramOutputValue {
-    pub fn identifier(&self) -> String {
-        format!("{}_{}_{}", self.market, self.coin_id, self.currency)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::{block_output_for_tests, wasm_hash_for_tests};
@@ -116,7 +148,7 @@ mod tests {
     #[test]
     fn test_decode_fn_call_claims() {
         let output = block_output_for_tests();
-        let ta = output.ta();
+        let ta = output.ta().unwrap();
         let data = ta.data();
 
         let claims = BlockyClaims::decode_fn_call_claims(&data)
@@ -136,6 +168,48 @@ mod tests {
         assert_eq!(output.currency, "USD");
         assert_eq!(output.price, 1056);
         assert_eq!(output.timestamp, 1755463157);
-        assert_eq!(output.identifier(), "Gate_CSPR_USD");
+    }
+
+    #[test]
+    fn test_guest_program_output_shapes() {
+        // Success.
+        let output = GuestProgramOutput::<GuestProgramOutputValue>::try_from_string(
+            r#"{"success":true,"error":"","value":{"market":"Gate","coin_id":"CSPR","currency":"USD","price":1,"timestamp":2}}"#,
+        )
+        .unwrap();
+        assert!(output.is_success());
+        assert_eq!(output.into_result().unwrap().price, 1);
+
+        // Failure written by `output.go`.
+        let output = GuestProgramOutput::<GuestProgramOutputValue>::try_from_string(
+            r#"{ "Success": false, "Error": "market Gate not found" , "Value": null }"#,
+        )
+        .unwrap();
+        assert!(!output.is_success());
+        assert_eq!(output.error_message(), "market Gate not found");
+        assert_eq!(
+            output.into_result().err(),
+            Some(BlockyClaimsError::GuestProgramFailed(String::from("market Gate not found")))
+        );
+
+        // Lowercase failure without a value.
+        let output = GuestProgramOutput::<GuestProgramOutputValue>::try_from_string(
+            r#"{"success":false,"error":"timeout"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            output.into_result().err(),
+            Some(BlockyClaimsError::GuestProgramFailed(String::from("timeout")))
+        );
+
+        // Success with a broken value.
+        let result = GuestProgramOutput::<GuestProgramOutputValue>::try_from_string(
+            r#"{"success":true,"error":"","value":{"market":"Gate"}}"#,
+        );
+        assert_eq!(result.err(), Some(BlockyClaimsError::OutputJsonDecoding));
+
+        // Not an output at all.
+        let result = GuestProgramOutput::<GuestProgramOutputValue>::try_from_string("[]");
+        assert_eq!(result.err(), Some(BlockyClaimsError::OutputJsonDecoding));
     }
 }
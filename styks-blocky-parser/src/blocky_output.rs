@@ -1,14 +1,69 @@
-use std::{fs, path::Path};
+use std::{fmt, fs, path::Path};
 
 use base64::{prelude::BASE64_STANDARD, Engine};
-use ethabi::{decode, ParamType};
 use k256::ecdsa::{RecoveryId, Signature, VerifyingKey, signature::hazmat::PrehashVerifier};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 
+use crate::{
+    abi::decode_bytes_array_owned,
+    blocky_claims::{BlockyClaims, BlockyClaimsError},
+};
 
 pub type DynError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockyOutputError {
+    PublicKeyBase64Decoding,
+    InvalidPublicKey,
+    TABase64Decoding,
+    TAAbiDecoding,
+    TAInvalidElementsCount(usize),
+    SignatureTooShort(usize),
+    InvalidSignature,
+    InvalidRecoveryId(u8),
+    PublicKeyRecovery,
+    OutputBase64Decoding,
+    Claims(BlockyClaimsError),
+    ClaimsMismatch(&'static str),
+}
+
+impl From<BlockyClaimsError> for BlockyOutputError {
+    fn from(error: BlockyClaimsError) -> Self {
+        BlockyOutputError::Claims(error)
+    }
+}
+
+impl fmt::Display for BlockyOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BlockyOutputError::*;
+        match self {
+            PublicKeyBase64Decoding => write!(f, "Failed to decode public key from base64"),
+            InvalidPublicKey => write!(f, "Failed to parse public key from SEC1 bytes"),
+            TABase64Decoding => write!(f, "Failed to decode TA from base64"),
+            TAAbiDecoding => write!(f, "Failed to decode TA data as bytes[]"),
+            TAInvalidElementsCount(count) => {
+                write!(f, "Expected 2 elements in TA data, got {}", count)
+            }
+            SignatureTooShort(len) => write!(
+                f,
+                "Signature too short, expected 65 bytes (r + s + v), got {}",
+                len
+            ),
+            InvalidSignature => write!(f, "Failed to create signature from r and s"),
+            InvalidRecoveryId(byte) => write!(f, "Invalid recovery ID byte: {}", byte),
+            PublicKeyRecovery => write!(f, "Failed to recover public key"),
+            OutputBase64Decoding => write!(f, "Failed to decode claims output from base64"),
+            Claims(error) => write!(f, "Failed to decode signed claims: {:?}", error),
+            ClaimsMismatch(field) => {
+                write!(f, "JSON claims don't match the signed ones: {}", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockyOutputError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockyOutput {
     pub enclave_attested_application_public_key: EnclaveAttestedApplicationPublicKey,
@@ -22,24 +77,48 @@ impl BlockyOutput {
         Ok(parsed)
     }
 
-    pub fn public_key(&self) -> VerifyingKey {
+    pub fn public_key(&self) -> Result<VerifyingKey, BlockyOutputError> {
         let public_key_str = &self.enclave_attested_application_public_key.claims.public_key.data;
-        let public_key_bytes = BASE64_STANDARD.decode(public_key_str).unwrap();
-        let public_key = VerifyingKey::from_sec1_bytes(&public_key_bytes)
-            .expect("Failed to parse original public key from SEC1 bytes");
-        public_key
+        let public_key_bytes = BASE64_STANDARD
+            .decode(public_key_str)
+            .map_err(|_| BlockyOutputError::PublicKeyBase64Decoding)?;
+        VerifyingKey::from_sec1_bytes(&public_key_bytes)
+            .map_err(|_| BlockyOutputError::InvalidPublicKey)
     }
 
-    pub fn public_key_bytes(&self) -> Vec<u8> {
-        self.public_key().to_sec1_bytes().to_vec()
+    pub fn public_key_bytes(&self) -> Result<Vec<u8>, BlockyOutputError> {
+        Ok(self.public_key()?.to_sec1_bytes().to_vec())
     }
 
-    pub fn ta(&self) -> TA {
+    pub fn ta(&self) -> Result<TA, BlockyOutputError> {
         let ta_data = &self.transitive_attested_function_call.transitive_attestation;
-        let ta_data = BASE64_STANDARD.decode(ta_data).expect("Failed to decode TA data");
+        let ta_data = BASE64_STANDARD
+            .decode(ta_data)
+            .map_err(|_| BlockyOutputError::TABase64Decoding)?;
         TA::new(&ta_data)
     }
 
+    // Claims decoded from the signed TA.
+    pub fn claims(&self) -> Result<BlockyClaims, BlockyOutputError> {
+        self.ta()?.claims()
+    }
+
+    // Checks the JSON claims match the signed ones.
+    pub fn verify_consistency(&self) -> Result<(), BlockyOutputError> {
+        let signed = self.claims()?;
+        let json = self.transitive_attested_function_call.claims.to_blocky_claims()?;
+        let fields = [
+            ("hash_of_code", &signed.hash_of_code, &json.hash_of_code),
+            ("function", &signed.function, &json.function),
+            ("hash_of_input", &signed.hash_of_input, &json.hash_of_input),
+            ("output", &signed.output, &json.output),
+            ("hash_of_secrets", &signed.hash_of_secrets, &json.hash_of_secrets),
+        ];
+        match fields.iter().find(|(_, signed, json)| signed != json) {
+            Some((field, _, _)) => Err(BlockyOutputError::ClaimsMismatch(field)),
+            None => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,30 +151,31 @@ pub struct TransitiveAttestedFunctionCall {
     pub claims: TransitiveClaims,
 }
 
+// JSON representation of the claims. It is not signed, so it should be
+// checked against the TA with `BlockyOutput::verify_consistency`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransitiveClaims {
     pub hash_of_code: String,
     pub function: String,
     pub hash_of_input: String,
-    // This field is a JSON string; use helper below to parse it if needed.
+    // Base64 encoded output of the guest program.
     pub output: String,
     pub hash_of_secrets: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FunctionOutput {
-    pub success: bool,
-    pub error: String,
-    pub value: FunctionOutputValue,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FunctionOutputValue {
-    pub market: String,
-    pub coin_id: String,
-    pub currency: String,
-    pub price: u64,
-    pub timestamp: u64,
+impl TransitiveClaims {
+    pub fn to_blocky_claims(&self) -> Result<BlockyClaims, BlockyOutputError> {
+        let output = BASE64_STANDARD
+            .decode(&self.output)
+            .map_err(|_| BlockyOutputError::OutputBase64Decoding)?;
+        Ok(BlockyClaims {
+            hash_of_code: self.hash_of_code.as_bytes().to_vec(),
+            function: self.function.as_bytes().to_vec(),
+            hash_of_input: self.hash_of_input.as_bytes().to_vec(),
+            output,
+            hash_of_secrets: self.hash_of_secrets.as_bytes().to_vec(),
+        })
+    }
 }
 
 pub struct TA {
@@ -105,47 +185,34 @@ pub struct TA {
 }
 
 impl TA {
-    pub fn new(bytes: &[u8]) -> Self {
-        let decoded = decode(&[ParamType::Array(Box::new(ParamType::Bytes))], bytes)
-            .expect("Failed to decode TA data");
-
-        let bytes_array = decoded[0].clone().into_array().expect("Expected array");
-        if bytes_array.len() != 2 {
-            panic!("Expected 2 elements in TA data, got {}", bytes_array.len());
-        }
-
-        let data = bytes_array[0]
-            .clone()
-            .into_bytes()
-            .expect("Expected bytes for data");
-        let sig_bytes = bytes_array[1]
-            .clone()
-            .into_bytes()
-            .expect("Expected bytes for signature");
+    pub fn new(bytes: &[u8]) -> Result<Self, BlockyOutputError> {
+        let bytes_array =
+            decode_bytes_array_owned(bytes).map_err(|_| BlockyOutputError::TAAbiDecoding)?;
+        let [data, sig_bytes]: [_; 2] = bytes_array
+            .try_into()
+            .map_err(|array: Vec<_>| BlockyOutputError::TAInvalidElementsCount(array.len()))?;
 
         if sig_bytes.len() < 65 {
-            panic!(
-                "Signature too short, expected 65 bytes (r + s + v), got {}",
-                sig_bytes.len()
-            );
+            return Err(BlockyOutputError::SignatureTooShort(sig_bytes.len()));
         }
 
         // The k256::Signature type is just r and s (64 bytes)
         let signature = Signature::from_slice(&sig_bytes[..64])
-            .expect("Failed to create signature from r and s");
+            .map_err(|_| BlockyOutputError::InvalidSignature)?;
 
         // The recovery ID is the last byte
-        let recovery_id = RecoveryId::from_byte(sig_bytes[64]).expect("Invalid recovery ID byte");
+        let recovery_id = RecoveryId::from_byte(sig_bytes[64])
+            .ok_or(BlockyOutputError::InvalidRecoveryId(sig_bytes[64]))?;
 
-        TA {
+        Ok(TA {
             data,
             signature,
             recovery_id,
-        }
+        })
     }
 
     // This function recovers the public key
-    pub fn recover_public_key(&self) -> VerifyingKey {
+    pub fn recover_public_key(&self) -> Result<VerifyingKey, BlockyOutputError> {
         // Hash the data using Keccak256, matching Solidity's keccak256()
         let mut hasher = Keccak256::new();
         hasher.update(&self.data);
@@ -153,7 +220,7 @@ impl TA {
 
         // Recover the verifying key (public key) from the hash and signature
         VerifyingKey::recover_from_prehash(&data_hash, &self.signature, self.recovery_id)
-            .expect("Failed to recover public key")
+            .map_err(|_| BlockyOutputError::PublicKeyRecovery)
 
         // // I wish this worked:
         // VerifyingKey::recover_from_msg(&self.data, &self.signature, self.recovery_id)
@@ -179,21 +246,167 @@ impl TA {
         self.signature().to_vec()
     }
 
+    // Signature with the recovery byte (r + s + v).
+    pub fn recoverable_signature_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.signature_bytes();
+        bytes.push(self.recovery_id.to_byte());
+        bytes
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data
     }
-}
 
+    pub fn claims(&self) -> Result<BlockyClaims, BlockyOutputError> {
+        Ok(BlockyClaims::decode_fn_call_claims(&self.data)?)
+    }
+}
 
 
 #[cfg(test)]
 mod tests {
-    use crate::block_output_for_tests;
+    use ethabi::{encode, Token};
+
+    use crate::{block_output_for_tests, TestRng};
+
+    use super::*;
+
+    fn encode_ta(elements: Vec<Vec<u8>>) -> Vec<u8> {
+        let tokens = elements.into_iter().map(Token::Bytes).collect();
+        encode(&[Token::Array(tokens)])
+    }
+
+    fn valid_ta_bytes() -> Vec<u8> {
+        let output = block_output_for_tests();
+        let ta = &output.transitive_attested_function_call.transitive_attestation;
+        BASE64_STANDARD.decode(ta).unwrap()
+    }
 
     #[test]
     fn test_load_blocky_output_from_file() {
         let output = block_output_for_tests();
         assert!(output.enclave_attested_application_public_key.enclave_attestation.len() > 0);
         assert!(output.transitive_attested_function_call.transitive_attestation.len() > 0);
+
+        let ta = output.ta().unwrap();
+        assert_eq!(ta.recover_public_key(), output.public_key());
+    }
+
+    #[test]
+    fn test_public_key_errors() {
+        let mut output = block_output_for_tests();
+        let claims = &mut output.enclave_attested_application_public_key.claims;
+
+        claims.public_key.data = String::from("not base64!");
+        assert_eq!(output.public_key(), Err(BlockyOutputError::PublicKeyBase64Decoding));
+
+        let claims = &mut output.enclave_attested_application_public_key.claims;
+        claims.public_key.data = BASE64_STANDARD.encode([4u8; 65]);
+        assert_eq!(output.public_key_bytes(), Err(BlockyOutputError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn test_ta_errors() {
+        let mut output = block_output_for_tests();
+        output.transitive_attested_function_call.transitive_attestation = String::from("%%%");
+        assert_eq!(output.ta().err(), Some(BlockyOutputError::TABase64Decoding));
+
+        assert_eq!(TA::new(&[]).err(), Some(BlockyOutputError::TAAbiDecoding));
+        assert_eq!(TA::new(&[1, 2, 3]).err(), Some(BlockyOutputError::TAAbiDecoding));
+
+        let valid_signature = {
+            let mut signature = vec![1u8; 64];
+            signature.push(0);
+            signature
+        };
+        let result = TA::new(&encode_ta(vec![vec![1, 2, 3]]));
+        assert_eq!(result.err(), Some(BlockyOutputError::TAInvalidElementsCount(1)));
+
+        let result = TA::new(&encode_ta(vec![vec![], valid_signature.clone(), vec![]]));
+        assert_eq!(result.err(), Some(BlockyOutputError::TAInvalidElementsCount(3)));
+
+        let result = TA::new(&encode_ta(vec![vec![], vec![1u8; 64]]));
+        assert_eq!(result.err(), Some(BlockyOutputError::SignatureTooShort(64)));
+
+        let result = TA::new(&encode_ta(vec![vec![], vec![0u8; 65]]));
+        assert_eq!(result.err(), Some(BlockyOutputError::InvalidSignature));
+
+        let mut bad_recovery_id = valid_signature.clone();
+        bad_recovery_id[64] = 27;
+        let result = TA::new(&encode_ta(vec![vec![], bad_recovery_id]));
+        assert_eq!(result.err(), Some(BlockyOutputError::InvalidRecoveryId(27)));
+
+        assert!(TA::new(&encode_ta(vec![vec![], valid_signature])).is_ok());
+    }
+
+    #[test]
+    fn test_verify_consistency() {
+        let output = block_output_for_tests();
+        assert_eq!(output.verify_consistency(), Ok(()));
+        let claims = output.claims().unwrap();
+        assert_eq!(
+            output.transitive_attested_function_call.claims.to_blocky_claims(),
+            Ok(claims)
+        );
+
+        // Tampering with any of the JSON claims is detected.
+        type Tamper = fn(&mut TransitiveClaims);
+        let tampered: [(&str, Tamper); 5] = [
+            ("hash_of_code", |claims| claims.hash_of_code.push('0')),
+            ("function", |claims| claims.function = String::from("otherFunc")),
+            ("hash_of_input", |claims| claims.hash_of_input.clear()),
+            ("output", |claims| claims.output = BASE64_STANDARD.encode("{}")),
+            ("hash_of_secrets", |claims| claims.hash_of_secrets.truncate(8)),
+        ];
+        for (field, tamper) in tampered {
+            let mut output = block_output_for_tests();
+            tamper(&mut output.transitive_attested_function_call.claims);
+            assert_eq!(
+                output.verify_consistency(),
+                Err(BlockyOutputError::ClaimsMismatch(field))
+            );
+        }
+
+        let mut output = block_output_for_tests();
+        output.transitive_attested_function_call.claims.output = String::from("%%%");
+        assert_eq!(
+            output.verify_consistency(),
+            Err(BlockyOutputError::OutputBase64Decoding)
+        );
+    }
+
+    #[test]
+    fn test_fuzz_mutated_ta() {
+        let valid = valid_ta_bytes();
+        let mut rng = TestRng(0x5eed);
+        for _ in 0..2000 {
+            let mut bytes = valid.clone();
+            for _ in 0..=rng.below(8) {
+                // Favor the head, where the offsets and lengths live.
+                let range = if rng.below(2) == 0 { 160 } else { bytes.len() };
+                let index = rng.below(range);
+                bytes[index] = rng.next() as u8;
+            }
+            if rng.below(4) == 0 {
+                bytes.truncate(rng.below(bytes.len()));
+            }
+            // Must never panic.
+            if let Ok(ta) = TA::new(&bytes) {
+                let _ = ta.recover_public_key();
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzz_random_ta() {
+        let mut rng = TestRng(0xb10c);
+        for _ in 0..2000 {
+            let len = rng.below(512);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            // Must never panic.
+            if let Ok(ta) = TA::new(&bytes) {
+                let _ = ta.recover_public_key();
+            }
+        }
     }
 }
@@ -6,10 +6,15 @@ extern crate alloc;
 #[cfg(feature = "std")]
 use sha3::{Digest, Sha3_512};
 
+pub mod abi;
+pub mod address_balance;
+pub mod attested_data;
 pub mod blocky_claims;
 
 #[cfg(feature = "std")]
 pub mod blocky_output;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod verify;
 
 #[cfg(feature = "std")]
@@ -32,6 +37,24 @@ pub fn wasm_hash_for_tests() -> String {
     wasm_hash(wasm)
 }
 
+// Simple xorshift generator, so the fuzz tests are deterministic.
+#[cfg(test)]
+pub(crate) struct TestRng(pub u64);
+
+#[cfg(test)]
+impl TestRng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,209 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    abi::encode_bytes_array,
+    blocky_output::{
+        BlockyOutput, EnclaveAttestedApplicationPublicKey,
+        EnclaveAttestedApplicationPublicKeyClaims, EnclaveMeasurement, PublicKey,
+        TransitiveAttestedFunctionCall, TransitiveClaims,
+    },
+    verify::{eth_address, EthAddress},
+    wasm_hash_for_tests,
+};
+
+// Local secp256k1 key standing in for the Blocky enclave key.
+pub struct TestSigner {
+    key: SigningKey,
+}
+
+impl TestSigner {
+    pub fn from_seed(seed: u8) -> Self {
+        let key = SigningKey::from_slice(&[seed; 32]).expect("Invalid seed");
+        TestSigner { key }
+    }
+
+    // SEC1 encoded, the same as `BlockyOutput::public_key_bytes`.
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        self.key.verifying_key().to_sec1_bytes().to_vec()
+    }
+
+    pub fn eth_address(&self) -> EthAddress {
+        eth_address(&self.public_key_bytes()).expect("Invalid public key")
+    }
+
+    // Signs the keccak256 hash of the data. Returns r + s + v.
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        let hash = Keccak256::digest(data);
+        let (signature, recovery_id) = self
+            .key
+            .sign_prehash_recoverable(&hash)
+            .expect("Failed to sign");
+        let mut bytes = signature.to_vec();
+        bytes.push(recovery_id.to_byte());
+        bytes
+    }
+}
+
+// Function call claims, signed the same way Blocky does.
+#[derive(Debug, Clone)]
+pub struct TestAttestation {
+    pub hash_of_code: String,
+    pub function: String,
+    pub hash_of_input: String,
+    // Raw JSON output of the guest program.
+    pub output: String,
+    pub hash_of_secrets: String,
+}
+
+impl TestAttestation {
+    // Successful call of the guest program, produced by `1_guest.wasm` by default.
+    pub fn new(function: &str, value_json: &str) -> Self {
+        TestAttestation {
+            hash_of_code: wasm_hash_for_tests(),
+            function: String::from(function),
+            hash_of_input: String::from("test_hash_of_input"),
+            output: format!(r#"{{"success":true,"error":"","value":{}}}"#, value_json),
+            hash_of_secrets: String::from("test_hash_of_secrets"),
+        }
+    }
+
+    // Output of the price guest program.
+    pub fn price(market: &str, coin_id: &str, currency: &str, price: u64, timestamp: u64) -> Self {
+        let value = format!(
+            r#"{{"market":"{}","coin_id":"{}","currency":"{}","price":{},"timestamp":{}}}"#,
+            market, coin_id, currency, price, timestamp
+        );
+        Self::new("priceFunc", &value)
+    }
+
+    // Output of the address balance guest program.
+    pub fn balance(chain: &str, address: &str, balance: u64, block_height: u64, timestamp: u64) -> Self {
+        let value = format!(
+            r#"{{"chain":"{}","address":"{}","balance":{},"block_height":{},"timestamp":{}}}"#,
+            chain, address, balance, block_height, timestamp
+        );
+        Self::new("balanceFunc", &value)
+    }
+
+    pub fn with_hash_of_code(mut self, hash_of_code: &str) -> Self {
+        self.hash_of_code = String::from(hash_of_code);
+        self
+    }
+
+    pub fn with_function(mut self, function: &str) -> Self {
+        self.function = String::from(function);
+        self
+    }
+
+    pub fn with_output(mut self, output: &str) -> Self {
+        self.output = String::from(output);
+        self
+    }
+
+    // ABI encoded claims, the signed TA data.
+    pub fn data(&self) -> Vec<u8> {
+        encode_bytes_array(&[
+            self.hash_of_code.as_bytes(),
+            self.function.as_bytes(),
+            self.hash_of_input.as_bytes(),
+            self.output.as_bytes(),
+            self.hash_of_secrets.as_bytes(),
+        ])
+    }
+
+    // Returns the 65 bytes signature and the data, as expected by contracts.
+    pub fn sign(&self, signer: &TestSigner) -> (Vec<u8>, Vec<u8>) {
+        let data = self.data();
+        (signer.sign(&data), data)
+    }
+
+    // Complete Blocky output, as returned by the Blocky CLI.
+    pub fn blocky_output(&self, signer: &TestSigner) -> BlockyOutput {
+        let (signature, data) = self.sign(signer);
+        let ta = encode_bytes_array(&[&data, &signature]);
+        let public_key = signer.key.verifying_key().to_encoded_point(false);
+
+        BlockyOutput {
+            enclave_attested_application_public_key: EnclaveAttestedApplicationPublicKey {
+                enclave_attestation: BASE64_STANDARD
+                    .encode(r#"{"platform":"local","platform_attestations":[]}"#),
+                claims: EnclaveAttestedApplicationPublicKeyClaims {
+                    enclave_measurement: EnclaveMeasurement {
+                        platform: String::from("local"),
+                        code: String::from("local"),
+                    },
+                    public_key: PublicKey {
+                        curve_type: String::from("p256k1"),
+                        data: BASE64_STANDARD.encode(public_key.as_bytes()),
+                    },
+                },
+            },
+            transitive_attested_function_call: TransitiveAttestedFunctionCall {
+                transitive_attestation: BASE64_STANDARD.encode(ta),
+                claims: TransitiveClaims {
+                    hash_of_code: self.hash_of_code.clone(),
+                    function: self.function.clone(),
+                    hash_of_input: self.hash_of_input.clone(),
+                    output: BASE64_STANDARD.encode(&self.output),
+                    hash_of_secrets: self.hash_of_secrets.clone(),
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{blocky_claims::BlockyClaims, verify::{recover_signer, verify_signature}};
+
+    use super::*;
+
+    #[test]
+    fn test_generated_blocky_output() {
+        let signer = TestSigner::from_seed(3);
+        let attestation = TestAttestation::price("Gate", "CSPR", "USD", 1234, 1700000000);
+        let output = attestation.blocky_output(&signer);
+
+        // The output is consistent and signed by the signer.
+        assert_eq!(output.verify_consistency(), Ok(()));
+        assert_eq!(output.public_key_bytes(), Ok(signer.public_key_bytes()));
+        let ta = output.ta().unwrap();
+        assert_eq!(ta.recover_public_key(), output.public_key());
+        let signature = ta.recoverable_signature_bytes();
+        assert_eq!(recover_signer(&signature, ta.data()), Ok(signer.eth_address()));
+
+        // The claims are decoded as usual.
+        let claims = output.claims().unwrap();
+        assert_eq!(claims.hash_of_code(), wasm_hash_for_tests());
+        assert_eq!(claims.function(), "priceFunc");
+        let value = claims.output().unwrap();
+        assert_eq!(
+            (value.market.as_str(), value.coin_id.as_str(), value.currency.as_str()),
+            ("Gate", "CSPR", "USD")
+        );
+        assert_eq!(value.price, 1234);
+        assert_eq!(value.timestamp, 1700000000);
+
+        // Survives the JSON round trip.
+        let json = serde_json_wasm::to_string(&output).unwrap();
+        let parsed: BlockyOutput = serde_json_wasm::from_str(&json).unwrap();
+        assert_eq!(parsed.claims(), Ok(claims));
+    }
+
+    #[test]
+    fn test_sign() {
+        let signer = TestSigner::from_seed(3);
+        let attestation = TestAttestation::new("dataFunc", "{}")
+            .with_hash_of_code("other_hash")
+            .with_function("otherFunc");
+        let (signature, data) = attestation.sign(&signer);
+        assert_eq!(verify_signature(&signer.public_key_bytes(), &signature, &data), Ok(()));
+
+        let claims = BlockyClaims::decode_fn_call_claims(&data).unwrap();
+        assert_eq!(claims.hash_of_code(), "other_hash");
+        assert_eq!(claims.function(), "otherFunc");
+        assert_eq!(claims.output_str(), r#"{"success":true,"error":"","value":{}}"#);
+    }
+}
@@ -1,14 +1,26 @@
-use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use k256::ecdsa::{signature::hazmat::PrehashVerifier, RecoveryId, Signature, VerifyingKey};
 use sha3::{Digest, Keccak256};
 
+pub type EthAddress = [u8; 20];
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum VerificationError {
     InvalidPublicKey,
     InvalidSignature,
     HashingError,
     BadSignature,
+    InvalidRecoveryId,
+    PublicKeyRecoveryFailed,
+    InvalidSignerAddress,
 }
 
+// Blocky signs the keccak256 hash of the data with ECDSA secp256k1, so the
+// verification runs in wasm. Casper host functions can't do it:
+// `casper_verify_signature` and `casper_recover_secp256k1` hash the message
+// with SHA-256 first, and `casper_generic_hash` has no keccak256.
 pub fn verify_signature(
     public_key: &[u8],
     signature: &[u8],
@@ -18,8 +30,12 @@ pub fn verify_signature(
     let public_key = VerifyingKey::from_sec1_bytes(public_key)
         .map_err(|_| VerificationError::InvalidPublicKey)?;
 
-    // Parse signature.
-    let signature = Signature::from_slice(&signature)
+    // Parse signature. The recovery byte is not needed here.
+    let signature = match signature.len() {
+        65 => &signature[..64],
+        _ => signature,
+    };
+    let signature = Signature::from_slice(signature)
         .map_err(|_| VerificationError::InvalidSignature)?;
 
     // Hash the data.
@@ -34,6 +50,75 @@ pub fn verify_signature(
     Ok(())
 }
 
+// Recovers the SEC1 encoded public key from the 65 bytes signature (r + s + v).
+pub fn recover_public_key(signature: &[u8], data: &[u8]) -> Result<Vec<u8>, VerificationError> {
+    let public_key = recover_verifying_key(signature, data)?;
+    Ok(public_key.to_sec1_bytes().to_vec())
+}
+
+// Recovers the Ethereum-style address of the signer, the same way Solidity's
+// `ecrecover` does.
+pub fn recover_signer(signature: &[u8], data: &[u8]) -> Result<EthAddress, VerificationError> {
+    let public_key = recover_verifying_key(signature, data)?;
+    Ok(eth_address_of(&public_key))
+}
+
+// Verifies the data is signed by the given Ethereum-style address.
+pub fn verify_signer(
+    signer: &[u8],
+    signature: &[u8],
+    data: &[u8],
+) -> Result<(), VerificationError> {
+    let signer: EthAddress = signer
+        .try_into()
+        .map_err(|_| VerificationError::InvalidSignerAddress)?;
+    if recover_signer(signature, data)? != signer {
+        return Err(VerificationError::BadSignature);
+    }
+    Ok(())
+}
+
+// Ethereum-style address of the SEC1 encoded public key.
+pub fn eth_address(public_key: &[u8]) -> Result<EthAddress, VerificationError> {
+    let public_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|_| VerificationError::InvalidPublicKey)?;
+    Ok(eth_address_of(&public_key))
+}
+
+fn recover_verifying_key(signature: &[u8], data: &[u8]) -> Result<VerifyingKey, VerificationError> {
+    if signature.len() != 65 {
+        return Err(VerificationError::InvalidSignature);
+    }
+    let signature_rs = Signature::from_slice(&signature[..64])
+        .map_err(|_| VerificationError::InvalidSignature)?;
+
+    // Accept both raw (0, 1) and Ethereum (27, 28) recovery bytes.
+    let v = match signature[64] {
+        v @ (27 | 28) => v - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_byte(v).ok_or(VerificationError::InvalidRecoveryId)?;
+
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    let data_hash = hasher.finalize();
+
+    VerifyingKey::recover_from_prehash(&data_hash, &signature_rs, recovery_id)
+        .map_err(|_| VerificationError::PublicKeyRecoveryFailed)
+}
+
+fn eth_address_of(public_key: &VerifyingKey) -> EthAddress {
+    // Keccak256 of the uncompressed key without the 0x04 prefix.
+    let point = public_key.to_encoded_point(false);
+    let mut hasher = Keccak256::new();
+    hasher.update(&point.as_bytes()[1..]);
+    let hash = hasher.finalize();
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{block_output_for_tests};
@@ -43,13 +128,13 @@ mod tests {
     #[test]
     fn test_verify_signature() {
         let output = block_output_for_tests();
-        let ta = output.ta();
+        let ta = output.ta().unwrap();
 
         // Test TA verification.
-        assert!(ta.verify_signature(&output.public_key()));
+        assert!(ta.verify_signature(&output.public_key().unwrap()));
 
         // Test standalone verification.
-        let public_key = output.public_key_bytes();
+        let public_key = output.public_key_bytes().unwrap();
         let signature = ta.signature_bytes();
         let data = ta.data();
         assert!(verify_signature(&public_key, &signature, &data).is_ok());
@@ -82,4 +167,49 @@ mod tests {
             Err(VerificationError::BadSignature)
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_recover_signer() {
+        let output = block_output_for_tests();
+        let ta = output.ta().unwrap();
+        let signature = ta.recoverable_signature_bytes();
+        let data = ta.data();
+        let public_key = output.public_key_bytes().unwrap();
+
+        // Recover the public key and the address.
+        assert_eq!(recover_public_key(&signature, data), Ok(public_key.clone()));
+        let signer = recover_signer(&signature, data).unwrap();
+        assert_eq!(eth_address(&public_key), Ok(signer));
+        assert_eq!(verify_signer(&signer, &signature, data), Ok(()));
+
+        // Ethereum style recovery byte.
+        let mut eth_signature = signature.clone();
+        eth_signature[64] += 27;
+        assert_eq!(recover_signer(&eth_signature, data), Ok(signer));
+
+        // 65 bytes signatures are accepted by `verify_signature` too.
+        assert_eq!(verify_signature(&public_key, &signature, data), Ok(()));
+
+        // Errors.
+        assert_eq!(
+            recover_signer(&signature[..64], data),
+            Err(VerificationError::InvalidSignature)
+        );
+        let mut bad_recovery_id = signature.clone();
+        bad_recovery_id[64] = 5;
+        assert_eq!(
+            recover_signer(&bad_recovery_id, data),
+            Err(VerificationError::InvalidRecoveryId)
+        );
+        assert_eq!(
+            verify_signer(&signer[..19], &signature, data),
+            Err(VerificationError::InvalidSignerAddress)
+        );
+        assert_eq!(
+            verify_signer(&[1u8; 20], &signature, data),
+            Err(VerificationError::BadSignature)
+        );
+        // Different data recovers a different signer.
+        assert_ne!(recover_signer(&signature, b"Different data"), Ok(signer));
+    }
+}
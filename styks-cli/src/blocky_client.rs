@@ -0,0 +1,407 @@
+//! Client of the Blocky attestation service, used in place of `bky-as`.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use styks_blocky_parser::blocky_output::BlockyOutput;
+
+// Endpoint of the function call attestation, relative to the host.
+pub const ATTEST_FN_CALL_PATH: &str = "/attest-fn-call";
+
+// Attestation runs the guest program in the enclave, which can take a while.
+const REQUEST_TIMEOUT_SECS: u64 = 120;
+
+#[derive(Debug, PartialEq)]
+pub enum BlockyClientError {
+    Io(String),
+    Config(String),
+    Http(String),
+    Status(u16, String),
+    Response(String),
+    UnacceptableMeasurement(String),
+}
+
+impl std::fmt::Display for BlockyClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockyClientError::Io(error) => write!(f, "Failed to read file: {}", error),
+            BlockyClientError::Config(error) => write!(f, "Invalid Blocky config: {}", error),
+            BlockyClientError::Http(error) => write!(f, "Blocky request failed: {}", error),
+            BlockyClientError::Status(status, body) => {
+                write!(f, "Blocky service returned status {}: {}", status, body)
+            }
+            BlockyClientError::Response(error) => {
+                write!(f, "Invalid Blocky response: {}", error)
+            }
+            BlockyClientError::UnacceptableMeasurement(code) => {
+                write!(f, "Enclave measurement is not acceptable: {}", code)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockyClientError {}
+
+// The `config.toml` of the guest program, shared with `bky-as`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlockyClientConfig {
+    pub host: String,
+    pub auth_token: String,
+    #[serde(default)]
+    pub acceptable_measurements: Vec<Measurement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Measurement {
+    pub platform: String,
+    pub code: String,
+}
+
+impl BlockyClientConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BlockyClientError> {
+        let content = read_to_string(path.as_ref())?;
+        Self::from_toml(&content)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, BlockyClientError> {
+        let config: BlockyClientConfig =
+            toml::from_str(content).map_err(|error| BlockyClientError::Config(error.to_string()))?;
+        // Without a measurement no enclave could be trusted.
+        if config.acceptable_measurements.is_empty() {
+            return Err(BlockyClientError::Config(String::from(
+                "acceptable_measurements must list at least one enclave measurement",
+            )));
+        }
+        Ok(config)
+    }
+}
+
+// The function call of `fn-call.json`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FnCall {
+    pub code_file: PathBuf,
+    pub function: String,
+    #[serde(default)]
+    pub input: serde_json::Value,
+    #[serde(default)]
+    pub secret: serde_json::Value,
+}
+
+impl FnCall {
+    // Loads the call, with `code_file` resolved against the file's directory.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BlockyClientError> {
+        let path = path.as_ref();
+        let content = read_to_string(path)?;
+        let mut call: FnCall = serde_json::from_str(&content)
+            .map_err(|error| BlockyClientError::Config(format!("{}: {}", path.display(), error)))?;
+        if let Some(dir) = path.parent() {
+            call.code_file = dir.join(&call.code_file);
+        }
+        Ok(call)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AttestFnCallRequest {
+    code: String,
+    function: String,
+    input: String,
+    secret: String,
+}
+
+pub struct BlockyClient {
+    config: BlockyClientConfig,
+    agent: ureq::Agent,
+}
+
+impl BlockyClient {
+    pub fn new(config: BlockyClientConfig) -> Self {
+        // Error statuses are read, so their bodies can be reported.
+        let agent_config = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))
+            .build();
+        BlockyClient {
+            config,
+            agent: ureq::Agent::new_with_config(agent_config),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BlockyClientError> {
+        Ok(Self::new(BlockyClientConfig::load(path)?))
+    }
+
+    // Runs the function call in the enclave and returns its attested output.
+    pub fn attest_fn_call(&self, call: &FnCall) -> Result<BlockyOutput, BlockyClientError> {
+        let code = std::fs::read(&call.code_file).map_err(|error| {
+            BlockyClientError::Io(format!("{}: {}", call.code_file.display(), error))
+        })?;
+        let request = AttestFnCallRequest {
+            code: BASE64_STANDARD.encode(code),
+            function: call.function.clone(),
+            input: BASE64_STANDARD.encode(call.input.to_string()),
+            secret: BASE64_STANDARD.encode(call.secret.to_string()),
+        };
+        let body = serde_json::to_string(&request)
+            .map_err(|error| BlockyClientError::Http(error.to_string()))?;
+
+        let url = format!("{}{}", self.config.host.trim_end_matches('/'), ATTEST_FN_CALL_PATH);
+        let mut response = self
+            .agent
+            .post(&url)
+            .header("Authorization", &format!("Bearer {}", self.config.auth_token))
+            .header("Content-Type", "application/json")
+            .send(body)
+            .map_err(|error| BlockyClientError::Http(error.to_string()))?;
+        let status = response.status().as_u16();
+        let body = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|error| BlockyClientError::Http(error.to_string()))?;
+        if !(200..300).contains(&status) {
+            return Err(BlockyClientError::Status(status, body.trim().to_string()));
+        }
+
+        let output: BlockyOutput = serde_json::from_str(&body)
+            .map_err(|error| BlockyClientError::Response(error.to_string()))?;
+        self.check_measurement(&output)?;
+        output
+            .verify_consistency()
+            .map_err(|error| BlockyClientError::Response(error.to_string()))?;
+        Ok(output)
+    }
+
+    // Like `bky-as`, only enclaves listed in the config are trusted.
+    fn check_measurement(&self, output: &BlockyOutput) -> Result<(), BlockyClientError> {
+        let measurement = &output
+            .enclave_attested_application_public_key
+            .claims
+            .enclave_measurement;
+        let acceptable = self.config.acceptable_measurements.iter().any(|accepted| {
+            accepted.platform == measurement.platform && accepted.code == measurement.code
+        });
+        match acceptable {
+            true => Ok(()),
+            false => Err(BlockyClientError::UnacceptableMeasurement(measurement.code.clone())),
+        }
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String, BlockyClientError> {
+    std::fs::read_to_string(path)
+        .map_err(|error| BlockyClientError::Io(format!("{}: {}", path.display(), error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    use super::*;
+
+    const TEST_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/test");
+
+    // Serves a single canned response and returns the request it got.
+    fn mock_server(status: u16, body: String) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            request.push_str(&String::from_utf8(request_body).unwrap());
+
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            request
+        });
+        (host, handle)
+    }
+
+    fn client(host: String, measurements: Vec<Measurement>) -> BlockyClient {
+        BlockyClient::new(BlockyClientConfig {
+            host,
+            auth_token: String::from("test-token"),
+            acceptable_measurements: measurements,
+        })
+    }
+
+    fn fn_call() -> FnCall {
+        FnCall {
+            code_file: PathBuf::from(TEST_DIR).join("1_guest.wasm"),
+            function: String::from("priceFunc"),
+            input: serde_json::json!({ "market": "Gate", "coin_id": "casper-network" }),
+            secret: serde_json::json!({ "api_key": "secret" }),
+        }
+    }
+
+    // `2_out.json` is an output saved by `bky-as`, not a captured exchange
+    // with the Blocky service. The mock assumes the service responds with the
+    // same JSON, `test_recorded_exchange` checks that against a recording.
+    fn mock_output() -> String {
+        std::fs::read_to_string(format!("{}/2_out.json", TEST_DIR)).unwrap()
+    }
+
+    // Measurement of the enclave that signed `2_out.json`.
+    fn mock_measurement() -> Measurement {
+        let output = BlockyOutput::try_from_file(format!("{}/2_out.json", TEST_DIR)).unwrap();
+        let measurement = output.enclave_attested_application_public_key.claims.enclave_measurement;
+        Measurement {
+            platform: measurement.platform,
+            code: measurement.code,
+        }
+    }
+
+    #[test]
+    fn test_load_config() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../blocky-guest/config.toml");
+        let config = BlockyClientConfig::load(path).unwrap();
+        assert!(config.host.starts_with("https://"));
+        assert_eq!(config.acceptable_measurements[0].platform, "nitro");
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../blocky-guest/fn-call.json.example");
+        let call = FnCall::load(path).unwrap();
+        assert_eq!(call.function, "priceFunc");
+        assert!(call.code_file.ends_with("blocky-guest/tmp/x.wasm"));
+        assert_eq!(call.input["market"], "Gate");
+
+        // At least one enclave measurement is required.
+        let config = BlockyClientConfig::from_toml("host = \"https://x\"\nauth_token = \"t\"");
+        assert!(matches!(config, Err(BlockyClientError::Config(_))));
+    }
+
+    #[test]
+    fn test_attest_fn_call() {
+        let (host, server) = mock_server(200, mock_output());
+        let output = client(host, vec![mock_measurement()]).attest_fn_call(&fn_call()).unwrap();
+        let expected = BlockyOutput::try_from_file(format!("{}/2_out.json", TEST_DIR)).unwrap();
+        assert_eq!(output.ta().unwrap().data(), expected.ta().unwrap().data());
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /attest-fn-call HTTP/1.1"));
+        assert!(request.to_lowercase().contains("authorization: bearer test-token"));
+        let body: serde_json::Value =
+            serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+        assert_eq!(body["function"], "priceFunc");
+        let input = BASE64_STANDARD.decode(body["input"].as_str().unwrap()).unwrap();
+        let input: serde_json::Value = serde_json::from_slice(&input).unwrap();
+        assert_eq!(input["coin_id"], "casper-network");
+    }
+
+    // Compares the request with an exchange of `bky-as` recorded by
+    // `make capture` in blocky-guest. Ignored until `blocky_exchange.json`
+    // is committed, no recording could be made without access to the service.
+    #[test]
+    #[ignore]
+    fn test_recorded_exchange() {
+        let content = std::fs::read_to_string(format!("{}/blocky_exchange.json", TEST_DIR))
+            .expect("Record the exchange with `make capture` in blocky-guest");
+        let exchanges: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let recorded = &exchanges[0];
+
+        // The recorded response is an attested output.
+        let output: BlockyOutput = serde_json::from_value(recorded["response"]["body"].clone())
+            .expect("The recorded response is not a BlockyOutput");
+        let measurement = output.enclave_attested_application_public_key.claims.enclave_measurement;
+        let measurement = Measurement {
+            platform: measurement.platform,
+            code: measurement.code,
+        };
+        let (host, server) = mock_server(200, recorded["response"]["body"].to_string());
+        client(host, vec![measurement]).attest_fn_call(&fn_call()).unwrap();
+        let request = server.join().unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+
+        // The recorded path includes the path of the host.
+        let mut lines = head.lines();
+        let request_line: Vec<&str> = lines.next().unwrap().split(' ').collect();
+        assert_eq!(request_line[0], recorded["request"]["method"]);
+        let path = recorded["request"]["path"].as_str().unwrap();
+        assert!(path.ends_with(request_line[1]), "{} is sent to {}", request_line[1], path);
+
+        // Headers are compared by name, and the auth scheme by value.
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+            .collect();
+        let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+        let recorded_headers = recorded["request"]["headers"].as_object().unwrap();
+        for name in ["authorization", "content-type"] {
+            assert!(recorded_headers.contains_key(name), "bky-as doesn't send {}", name);
+            assert!(header(name).is_some(), "{} is not sent", name);
+        }
+        let scheme = |value: &str| value.split(' ').next().unwrap_or_default().to_string();
+        let recorded_auth = recorded_headers["authorization"].as_str().unwrap();
+        assert_eq!(scheme(header("authorization").unwrap()), scheme(recorded_auth));
+
+        // The body has the same keys.
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        let keys = |value: &serde_json::Value| {
+            let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(&body), keys(&recorded["request"]["body"]));
+    }
+
+    #[test]
+    fn test_attest_fn_call_errors() {
+        // Error statuses keep the body.
+        let (host, server) = mock_server(401, String::from("{\"error\":\"invalid token\"}"));
+        assert_eq!(
+            client(host, vec![]).attest_fn_call(&fn_call()).map(|_| ()),
+            Err(BlockyClientError::Status(401, String::from("{\"error\":\"invalid token\"}")))
+        );
+        server.join().unwrap();
+
+        // Unknown enclaves are rejected, and so is everything without measurements.
+        let measurement = Measurement {
+            platform: String::from("nitro"),
+            code: String::from("other"),
+        };
+        for measurements in [vec![measurement], vec![]] {
+            let (host, server) = mock_server(200, mock_output());
+            let result = client(host, measurements).attest_fn_call(&fn_call());
+            assert!(matches!(result, Err(BlockyClientError::UnacceptableMeasurement(_))));
+            server.join().unwrap();
+        }
+
+        // Not an attestation.
+        let (host, server) = mock_server(200, String::from("{}"));
+        let result = client(host, vec![]).attest_fn_call(&fn_call());
+        assert!(matches!(result, Err(BlockyClientError::Response(_))));
+        server.join().unwrap();
+
+        // The guest program must exist.
+        let mut call = fn_call();
+        call.code_file = PathBuf::from("missing.wasm");
+        let result = client(String::from("http://127.0.0.1:1"), vec![]).attest_fn_call(&call);
+        assert!(matches!(result, Err(BlockyClientError::Io(_))));
+    }
+}
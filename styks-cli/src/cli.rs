@@ -1,10 +1,22 @@
 //! This example demonstrates how to use the `odra-cli` tool to deploy and interact with a smart contract.
 
 use odra::{contract_def::HasIdent, host::{HostEnv, InstallConfig, NoArgs}};
-use odra_cli::{cspr, deploy::DeployScript, DeployedContractsContainer, DeployerExt, OdraCli};
+use odra_cli::{deploy::DeployScript, DeployedContractsContainer, DeployerExt, OdraCli};
 use styks_contracts::{styks_blocky_supplier::StyksBlockySupplier, styks_price_feed::StyksPriceFeed};
 
+use crate::gas::{GasPolicy, DEFAULT_GAS_CONFIG, DEPLOY};
+
+mod blocky_client;
+mod clock;
+mod deployment;
+mod feed_status;
+mod gas;
+mod price_source;
+mod producer;
+mod roles;
 mod scenarios;
+mod schedule;
+mod verifier;
 
 pub struct ContractsDeployScript;
 impl DeployScript for ContractsDeployScript {
@@ -13,19 +25,29 @@ impl DeployScript for ContractsDeployScript {
         env: &HostEnv,
         container: &mut DeployedContractsContainer,
     ) -> Result<(), odra_cli::deploy::Error> {
+        let mut gas = GasPolicy::load(DEFAULT_GAS_CONFIG).map_err(|error| {
+            odra_cli::deploy::Error::OdraError { message: error.to_string() }
+        })?;
+
         let cfg = InstallConfig {
             package_named_key: StyksPriceFeed::ident(),
             is_upgradable: true,
             allow_key_override: true,
         };
-        StyksPriceFeed::load_or_deploy_with_cfg(env, NoArgs, cfg, container, cspr!(400))?;
+        let limit = gas.limit(&StyksPriceFeed::ident(), DEPLOY).motes;
+        gas.measure(env, &StyksPriceFeed::ident(), DEPLOY, limit, || {
+            StyksPriceFeed::load_or_deploy_with_cfg(env, NoArgs, cfg, container, limit)
+        })?;
 
         let cfg = InstallConfig {
             package_named_key: StyksBlockySupplier::ident(),
             is_upgradable: true,
             allow_key_override: true,
         };
-        StyksBlockySupplier::load_or_deploy_with_cfg(env, NoArgs, cfg, container, cspr!(600))?;
+        let limit = gas.limit(&StyksBlockySupplier::ident(), DEPLOY).motes;
+        gas.measure(env, &StyksBlockySupplier::ident(), DEPLOY, limit, || {
+            StyksBlockySupplier::load_or_deploy_with_cfg(env, NoArgs, cfg, container, limit)
+        })?;
         Ok(())
     }
 }
@@ -40,7 +62,9 @@ pub fn main() {
         .scenario(scenarios::SetPermissions)
         .scenario(scenarios::SetConfig)
         .scenario(scenarios::UpdatePrice)
-        // .scenario(scenarios::ListFeed)
+        .scenario(scenarios::ShowFeed)
+        .scenario(scenarios::VerifyReport)
+        .scenario(scenarios::PlanSchedule)
         .build()
         .run();
 }
@@ -0,0 +1,11 @@
+//! Wall clock of the machine the CLI runs on.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Current Unix time in seconds.
+pub fn current_timestamp_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
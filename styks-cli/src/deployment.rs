@@ -0,0 +1,360 @@
+//! Declarative deployment config of the Styks contracts.
+
+use std::{fmt::Debug, path::Path};
+
+use odra::{casper_types::bytesrepr::Bytes, prelude::Address};
+use serde::Deserialize;
+use styks_contracts::{
+    styks_blocky_supplier::{
+        StyksBlockySupplerConfig, StyksBlockySupplerExpectation, StyksBlockySupplerQuorumConfig,
+    },
+    styks_price_feed::StyksPriceFeedConfig,
+};
+use styks_core::{PriceFeedId, SourceKey};
+
+pub const DEFAULT_DEPLOYMENT_CONFIG: &str = "resources/deployment.toml";
+
+#[derive(Debug)]
+pub enum DeploymentConfigError {
+    Io(String),
+    Parse(String),
+    InvalidHex(&'static str),
+}
+
+impl std::fmt::Display for DeploymentConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeploymentConfigError::Io(error) => write!(f, "Failed to read config: {}", error),
+            DeploymentConfigError::Parse(error) => write!(f, "Failed to parse config: {}", error),
+            DeploymentConfigError::InvalidHex(field) => write!(f, "Invalid hex in `{}`", field),
+        }
+    }
+}
+
+impl std::error::Error for DeploymentConfigError {}
+
+// Desired state of both contracts, read from a TOML or JSON file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DeploymentConfig {
+    pub price_feed: PriceFeedDeployment,
+    pub supplier: SupplierDeployment,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PriceFeedDeployment {
+    pub heartbeat_interval: u64,
+    pub heartbeat_tolerance: u64,
+    pub twap_window: u32,
+    pub twap_tolerance: u32,
+    pub price_feed_ids: Vec<PriceFeedId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SupplierDeployment {
+    pub wasm_hash: String,
+    // Hex encoded SEC1 public key.
+    pub public_key: String,
+    // Hex encoded 20 bytes address.
+    #[serde(default)]
+    pub signer_address: Option<String>,
+    pub timestamp_tolerance: u64,
+    #[serde(default)]
+    pub quorum: Option<QuorumDeployment>,
+    #[serde(default)]
+    pub expectations: Vec<ExpectationDeployment>,
+    #[serde(default)]
+    pub mappings: Vec<MappingDeployment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QuorumDeployment {
+    pub threshold: u32,
+    pub tolerance_bps: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExpectationDeployment {
+    pub price_feed_id: PriceFeedId,
+    pub market: String,
+    pub coin_id: String,
+    pub currency: String,
+    pub min_price: u64,
+    pub max_price: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MappingDeployment {
+    pub market: String,
+    pub coin_id: String,
+    pub currency: String,
+    pub price_feed_id: PriceFeedId,
+}
+
+impl DeploymentConfig {
+    // Loads JSON for `.json` files and TOML otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DeploymentConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| DeploymentConfigError::Io(format!("{}: {}", path.display(), error)))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&content),
+            _ => Self::from_toml(&content),
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, DeploymentConfigError> {
+        toml::from_str(content).map_err(|error| DeploymentConfigError::Parse(error.to_string()))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, DeploymentConfigError> {
+        serde_json::from_str(content)
+            .map_err(|error| DeploymentConfigError::Parse(error.to_string()))
+    }
+
+    pub fn feed_config(&self) -> StyksPriceFeedConfig {
+        let feed = &self.price_feed;
+        StyksPriceFeedConfig {
+            heartbeat_interval: feed.heartbeat_interval,
+            heartbeat_tolerance: feed.heartbeat_tolerance,
+            twap_window: feed.twap_window,
+            twap_tolerance: feed.twap_tolerance,
+            price_feed_ids: feed.price_feed_ids.clone(),
+        }
+    }
+
+    pub fn supplier_config(
+        &self,
+        price_feed_address: Address,
+    ) -> Result<StyksBlockySupplerConfig, DeploymentConfigError> {
+        let supplier = &self.supplier;
+        let signer_address = match &supplier.signer_address {
+            Some(address) => Some(decode_hex(address, "supplier.signer_address")?),
+            None => None,
+        };
+        Ok(StyksBlockySupplerConfig {
+            wasm_hash: supplier.wasm_hash.clone(),
+            public_key: decode_hex(&supplier.public_key, "supplier.public_key")?,
+            price_feed_address,
+            timestamp_tolerance: supplier.timestamp_tolerance,
+            quorum: supplier.quorum.as_ref().map(|quorum| StyksBlockySupplerQuorumConfig {
+                threshold: quorum.threshold,
+                tolerance_bps: quorum.tolerance_bps,
+            }),
+            signer_address,
+            expectations: supplier
+                .expectations
+                .iter()
+                .map(|e| StyksBlockySupplerExpectation {
+                    price_feed_id: e.price_feed_id.clone(),
+                    market: e.market.clone(),
+                    coin_id: e.coin_id.clone(),
+                    currency: e.currency.clone(),
+                    min_price: e.min_price,
+                    max_price: e.max_price,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn mappings(&self) -> Vec<(SourceKey, PriceFeedId)> {
+        self.supplier
+            .mappings
+            .iter()
+            .map(|m| {
+                let key = SourceKey::new(&m.market, &m.coin_id, &m.currency);
+                (key, m.price_feed_id.clone())
+            })
+            .collect()
+    }
+}
+
+fn decode_hex(value: &str, field: &'static str) -> Result<Bytes, DeploymentConfigError> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(value)
+        .map(Bytes::from)
+        .map_err(|_| DeploymentConfigError::InvalidHex(field))
+}
+
+// --- Diff ---
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub current: String,
+    pub desired: String,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.current, self.desired)
+    }
+}
+
+#[derive(Default)]
+struct Diff(Vec<FieldChange>);
+
+impl Diff {
+    fn field<T: Debug + PartialEq>(&mut self, field: &'static str, current: Option<&T>, desired: &T) {
+        if current == Some(desired) {
+            return;
+        }
+        let current = match current {
+            Some(current) => format!("{:?}", current),
+            None => String::from("<unset>"),
+        };
+        self.0.push(FieldChange { field, current, desired: format!("{:?}", desired) });
+    }
+}
+
+pub fn diff_feed_config(
+    current: Option<&StyksPriceFeedConfig>,
+    desired: &StyksPriceFeedConfig,
+) -> Vec<FieldChange> {
+    let mut diff = Diff::default();
+    diff.field("heartbeat_interval", current.map(|c| &c.heartbeat_interval), &desired.heartbeat_interval);
+    diff.field("heartbeat_tolerance", current.map(|c| &c.heartbeat_tolerance), &desired.heartbeat_tolerance);
+    diff.field("twap_window", current.map(|c| &c.twap_window), &desired.twap_window);
+    diff.field("twap_tolerance", current.map(|c| &c.twap_tolerance), &desired.twap_tolerance);
+    diff.field("price_feed_ids", current.map(|c| &c.price_feed_ids), &desired.price_feed_ids);
+    diff.0
+}
+
+pub fn diff_supplier_config(
+    current: Option<&StyksBlockySupplerConfig>,
+    desired: &StyksBlockySupplerConfig,
+) -> Vec<FieldChange> {
+    let mut diff = Diff::default();
+    diff.field("wasm_hash", current.map(|c| &c.wasm_hash), &desired.wasm_hash);
+    diff.field("public_key", current.map(|c| &c.public_key), &desired.public_key);
+    diff.field("price_feed_address", current.map(|c| &c.price_feed_address), &desired.price_feed_address);
+    diff.field("timestamp_tolerance", current.map(|c| &c.timestamp_tolerance), &desired.timestamp_tolerance);
+    diff.field("quorum", current.map(|c| &c.quorum), &desired.quorum);
+    diff.field("signer_address", current.map(|c| &c.signer_address), &desired.signer_address);
+    diff.field("expectations", current.map(|c| &c.expectations), &desired.expectations);
+    diff.0
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MappingsDiff {
+    // New mappings, including the ones pointing to a different feed.
+    pub added: Vec<(SourceKey, PriceFeedId)>,
+    // Mappings missing in the desired state or pointing to a different feed.
+    pub removed: Vec<(SourceKey, PriceFeedId)>,
+}
+
+impl MappingsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+// Changed mappings are removed and added again, so the supplier never points
+// a source to a PriceFeedId the feed no longer lists.
+pub fn diff_mappings(
+    current: &[(SourceKey, PriceFeedId)],
+    desired: &[(SourceKey, PriceFeedId)],
+) -> MappingsDiff {
+    MappingsDiff {
+        added: desired.iter().filter(|m| !current.contains(m)).cloned().collect(),
+        removed: current.iter().filter(|m| !desired.contains(m)).cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> DeploymentConfig {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../", "resources/deployment.toml");
+        DeploymentConfig::load(path).expect("Failed to load the example config")
+    }
+
+    #[test]
+    fn test_load_example() {
+        let config = example();
+        assert_eq!(config.feed_config().price_feed_ids, vec![String::from("CSPRUSD")]);
+        assert_eq!(config.supplier.wasm_hash, styks_blocky_parser::wasm_hash_for_tests());
+        assert_eq!(
+            config.mappings(),
+            vec![(SourceKey::new("Gate", "CSPR", "USD"), String::from("CSPRUSD"))]
+        );
+
+        // The public key is the one of the test attestation.
+        let address = Address::new(
+            "hash-2879d6e927289197aab0101cc033f532fe22e4ab4686e44b5743cb1333031acc",
+        )
+        .unwrap();
+        let supplier_config = config.supplier_config(address).unwrap();
+        let public_key = styks_blocky_parser::block_output_for_tests().public_key_bytes().unwrap();
+        assert_eq!(supplier_config.public_key, Bytes::from(public_key));
+        assert_eq!(supplier_config.signer_address, None);
+    }
+
+    #[test]
+    fn test_json_and_errors() {
+        let json = r#"{
+            "price_feed": {
+                "heartbeat_interval": 600, "heartbeat_tolerance": 30,
+                "twap_window": 1, "twap_tolerance": 0, "price_feed_ids": ["BTCUSD"]
+            },
+            "supplier": {
+                "wasm_hash": "ab", "public_key": "0x02", "signer_address": "zz",
+                "timestamp_tolerance": 60
+            }
+        }"#;
+        let config = DeploymentConfig::from_json(json).unwrap();
+        assert_eq!(config.feed_config().heartbeat_interval, 600);
+        assert!(config.mappings().is_empty());
+        let address = Address::new(
+            "hash-2879d6e927289197aab0101cc033f532fe22e4ab4686e44b5743cb1333031acc",
+        )
+        .unwrap();
+        assert!(matches!(
+            config.supplier_config(address),
+            Err(DeploymentConfigError::InvalidHex("supplier.signer_address"))
+        ));
+
+        assert!(matches!(
+            DeploymentConfig::from_toml("[price_feed]"),
+            Err(DeploymentConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            DeploymentConfig::load("missing.toml"),
+            Err(DeploymentConfigError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_diff() {
+        let config = example();
+        let desired = config.feed_config();
+
+        // Nothing is set yet.
+        let changes = diff_feed_config(None, &desired);
+        assert_eq!(changes.len(), 5);
+        assert_eq!(changes[0].to_string(), "heartbeat_interval: <unset> -> 1800");
+
+        // Only the changed fields are listed.
+        let mut current = desired.clone();
+        assert!(diff_feed_config(Some(&current), &desired).is_empty());
+        current.twap_window = 5;
+        assert_eq!(
+            diff_feed_config(Some(&current), &desired),
+            vec![FieldChange {
+                field: "twap_window",
+                current: String::from("5"),
+                desired: String::from("3"),
+            }]
+        );
+
+        // Mappings.
+        let gate = (SourceKey::new("Gate", "CSPR", "USD"), String::from("CSPRUSD"));
+        let moved = (SourceKey::new("Gate", "CSPR", "USD"), String::from("CSPRUSD2"));
+        let kraken = (SourceKey::new("Kraken", "CSPR", "USD"), String::from("CSPRUSD"));
+        let diff = diff_mappings(&[gate.clone(), kraken.clone()], std::slice::from_ref(&moved));
+        assert_eq!(diff.added, vec![moved.clone()]);
+        assert_eq!(diff.removed, vec![gate, kraken]);
+        let moved = [moved];
+        assert!(diff_mappings(&moved, &moved).is_empty());
+    }
+}
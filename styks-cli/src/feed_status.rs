@@ -0,0 +1,242 @@
+//! Read-only status of the StyksPriceFeed contract.
+
+use serde::Serialize;
+use styks_contracts::styks_price_feed::StyksPriceFeedConfig;
+use styks_core::{
+    heartbeat::{Heartbeat, HeartbeatError, HeartbeatWindow},
+    Price, PriceFeedId,
+};
+
+use crate::price_source::PRICE_SCALE;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedStatus {
+    pub current_time: u64,
+    pub config: ConfigStatus,
+    pub last_heartbeat: Option<u64>,
+    // Seconds since the last heartbeat.
+    pub last_heartbeat_age: Option<u64>,
+    pub missed_heartbeats: u64,
+    pub previous_window: Option<WindowStatus>,
+    pub current_window: Option<WindowStatus>,
+    pub next_window: WindowStatus,
+    pub feeds: Vec<PriceFeedStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigStatus {
+    pub heartbeat_interval: u64,
+    pub heartbeat_tolerance: u64,
+    pub twap_window: u32,
+    pub twap_tolerance: u32,
+    pub price_feed_ids: Vec<PriceFeedId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowStatus {
+    pub start: u64,
+    pub middle: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceFeedStatus {
+    pub price_feed_id: PriceFeedId,
+    // Stored TWAP values, oldest first. `None` marks a missed heartbeat.
+    pub twap_store: Vec<Option<Price>>,
+    pub twap_price: Option<Price>,
+}
+
+impl From<&HeartbeatWindow> for WindowStatus {
+    fn from(window: &HeartbeatWindow) -> Self {
+        WindowStatus {
+            start: window.start,
+            middle: window.middle,
+            end: window.end,
+        }
+    }
+}
+
+impl From<&StyksPriceFeedConfig> for ConfigStatus {
+    fn from(config: &StyksPriceFeedConfig) -> Self {
+        ConfigStatus {
+            heartbeat_interval: config.heartbeat_interval,
+            heartbeat_tolerance: config.heartbeat_tolerance,
+            twap_window: config.twap_window,
+            twap_tolerance: config.twap_tolerance,
+            price_feed_ids: config.price_feed_ids.clone(),
+        }
+    }
+}
+
+impl FeedStatus {
+    pub fn new(
+        current_time: u64,
+        config: &StyksPriceFeedConfig,
+        last_heartbeat: Option<u64>,
+        feeds: Vec<PriceFeedStatus>,
+    ) -> Result<Self, HeartbeatError> {
+        let heartbeat = Heartbeat::new(
+            current_time,
+            config.heartbeat_interval,
+            config.heartbeat_tolerance,
+        )?;
+        let state = heartbeat.current_state();
+        Ok(FeedStatus {
+            current_time,
+            config: ConfigStatus::from(config),
+            last_heartbeat,
+            last_heartbeat_age: last_heartbeat.map(|time| current_time.saturating_sub(time)),
+            // Same count as the feed uses for the TWAP.
+            missed_heartbeats: heartbeat
+                .count_missed_heartbeats_since(last_heartbeat.unwrap_or_default()),
+            previous_window: state.previous.as_ref().map(WindowStatus::from),
+            current_window: state.current.as_ref().map(WindowStatus::from),
+            next_window: WindowStatus::from(&state.next),
+            feeds,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let config = &self.config;
+        let mut lines = vec![
+            format!("Current time: {}", self.current_time),
+            format!(
+                "Config: heartbeat every {}s ±{}s, TWAP of {} values with up to {} missing, feeds: {}",
+                config.heartbeat_interval,
+                config.heartbeat_tolerance,
+                config.twap_window,
+                config.twap_tolerance,
+                config.price_feed_ids.join(", ")
+            ),
+        ];
+        lines.push(match (self.last_heartbeat, self.last_heartbeat_age) {
+            (Some(time), Some(age)) => {
+                format!("Last heartbeat: {} ({} ago)", time, parse_duration(age))
+            }
+            _ => String::from("Last heartbeat: never"),
+        });
+        lines.push(format!("Missed heartbeats: {}", self.missed_heartbeats));
+        lines.push(match &self.current_window {
+            Some(window) => format!("Current window: open, {}", format_window(window)),
+            None => String::from("Current window: closed"),
+        });
+        lines.push(format!(
+            "Next window: {} (in {})",
+            format_window(&self.next_window),
+            parse_duration(self.next_window.start.saturating_sub(self.current_time))
+        ));
+        for feed in &self.feeds {
+            let values: Vec<String> = feed
+                .twap_store
+                .iter()
+                .map(|value| match value {
+                    Some(price) => format_price(*price),
+                    None => String::from("missing"),
+                })
+                .collect();
+            lines.push(format!(
+                "[{}] TWAP: {}, stored values: [{}]",
+                feed.price_feed_id,
+                feed.twap_price.map(format_price).unwrap_or_else(|| String::from("none")),
+                values.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+fn format_window(window: &WindowStatus) -> String {
+    format!("{} - {} - {}", window.start, window.middle, window.end)
+}
+
+fn format_price(price: Price) -> String {
+    format!("${}", price as f64 / PRICE_SCALE)
+}
+
+// Returns a human-readable duration string:
+// e.g. "1 hour", "2 days", "3 months", "45 seconds".
+// or more complex like "1 year, 2 months, 3 days".
+pub fn parse_duration(duration: u64) -> String {
+    if duration == 0 {
+        return String::from("0 seconds");
+    }
+    let units = [
+        ("year", duration / 31536000),
+        ("month", (duration / 2592000) % 12),
+        ("day", (duration / 86400) % 30),
+        ("hour", (duration / 3600) % 24),
+        ("minute", (duration / 60) % 60),
+        ("second", duration % 60),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(unit, count)| format!("{} {}{}", count, unit, if *count > 1 { "s" } else { "" }))
+        .collect();
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StyksPriceFeedConfig {
+        StyksPriceFeedConfig {
+            heartbeat_interval: 100,
+            heartbeat_tolerance: 10,
+            twap_window: 3,
+            twap_tolerance: 1,
+            price_feed_ids: vec![String::from("CSPRUSD")],
+        }
+    }
+
+    fn feed() -> PriceFeedStatus {
+        PriceFeedStatus {
+            price_feed_id: String::from("CSPRUSD"),
+            twap_store: vec![Some(1000), None, Some(1500)],
+            twap_price: Some(1250),
+        }
+    }
+
+    #[test]
+    fn test_feed_status() {
+        let status = FeedStatus::new(395, &config(), Some(100), vec![feed()]).unwrap();
+        assert_eq!(status.last_heartbeat_age, Some(295));
+        assert_eq!(status.missed_heartbeats, 2);
+        assert_eq!(status.current_window, Some(WindowStatus { start: 390, middle: 400, end: 410 }));
+        assert_eq!(status.next_window.middle, 500);
+
+        let lines = status.to_lines();
+        assert_eq!(lines[2], "Last heartbeat: 100 (4 minutes, 55 seconds ago)");
+        assert_eq!(lines[4], "Current window: open, 390 - 400 - 410");
+        assert_eq!(lines[6], "[CSPRUSD] TWAP: $0.0125, stored values: [$0.01, missing, $0.015]");
+
+        let json: serde_json::Value = serde_json::from_str(&status.to_json()).unwrap();
+        assert_eq!(json["feeds"][0]["twap_store"], serde_json::json!([1000, null, 1500]));
+        assert_eq!(json["current_window"]["end"], 410);
+
+        // Never updated and outside of a window.
+        let status = FeedStatus::new(50, &config(), None, vec![]).unwrap();
+        let lines = status.to_lines();
+        assert_eq!(lines[2], "Last heartbeat: never");
+        assert_eq!(lines[4], "Current window: closed");
+        assert_eq!(lines[5], "Next window: 90 - 100 - 110 (in 40 seconds)");
+
+        // The config on chain can't be invalid, but the status doesn't panic.
+        let mut invalid = config();
+        invalid.heartbeat_interval = 0;
+        assert!(FeedStatus::new(50, &invalid, None, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration(0), "0 seconds");
+        assert_eq!(parse_duration(61), "1 minute, 1 second");
+        assert_eq!(parse_duration(2 * 86400 + 7200), "2 days, 2 hours");
+    }
+}
@@ -0,0 +1,464 @@
+//! Gas limits of the contract calls and deploys sent by the CLI.
+//!
+//! Limits default to the per entry point values of the gas config. The
+//! livenet host of Odra doesn't expose speculative execution, so estimates
+//! are based on the costs recorded by earlier calls instead: the highest of
+//! the recent costs times the safety margin, at most the default limit.
+//! Costs recorded on a local network work as a simulation for the first
+//! calls on another one.
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use odra::host::HostEnv;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::current_timestamp_secs;
+
+pub const DEFAULT_GAS_CONFIG: &str = "resources/gas.toml";
+
+// Entry point name used for contract deploys.
+pub const DEPLOY: &str = "deploy";
+
+const MOTES_PER_CSPR: f64 = 1_000_000_000.0;
+
+#[derive(Debug, PartialEq)]
+pub enum GasConfigError {
+    Io(String),
+    Parse(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for GasConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasConfigError::Io(error) => write!(f, "Failed to read gas config: {}", error),
+            GasConfigError::Parse(error) => write!(f, "Failed to parse gas config: {}", error),
+            GasConfigError::Invalid(error) => write!(f, "Invalid gas config: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for GasConfigError {}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GasConfig {
+    // Use recorded costs to estimate the limits.
+    pub estimate: bool,
+    // Multiplier applied to the estimates.
+    pub safety_margin: f64,
+    // Upper bound of the configured limits, in CSPR.
+    pub max_limit: f64,
+    // Limit of the entry points missing from `limits`, in CSPR.
+    pub default_limit: f64,
+    // Number of recent costs per entry point an estimate looks at.
+    pub history: usize,
+    // Where the costs are recorded. Nothing is recorded if not set.
+    pub log: Option<PathBuf>,
+    // Default limits in CSPR, per contract and entry point.
+    pub limits: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        GasConfig {
+            estimate: false,
+            safety_margin: 1.5,
+            max_limit: 1000.0,
+            default_limit: 5.0,
+            history: 10,
+            log: None,
+            limits: default_limits(),
+        }
+    }
+}
+
+// The limits the CLI used before they were configurable.
+fn default_limits() -> BTreeMap<String, BTreeMap<String, f64>> {
+    let feed = [
+        (DEPLOY, 400.0),
+        ("set_config", 4.0),
+        ("add_to_feed", 2.5),
+        ("grant_role", 2.5),
+        ("revoke_role", 2.5),
+    ];
+    let supplier = [
+        (DEPLOY, 600.0),
+        ("set_config", 3.5),
+        ("report_signed_prices", 4.0),
+        ("report_signed_prices_batch", 10.0),
+        ("add_feed_mapping", 1.0),
+        ("remove_feed_mapping", 1.0),
+        ("grant_role", 2.5),
+        ("revoke_role", 2.5),
+    ];
+    let to_map = |limits: &[(&str, f64)]| {
+        limits
+            .iter()
+            .map(|(entry_point, limit)| (entry_point.to_string(), *limit))
+            .collect()
+    };
+    BTreeMap::from([
+        (String::from("StyksPriceFeed"), to_map(&feed)),
+        (String::from("StyksBlockySupplier"), to_map(&supplier)),
+    ])
+}
+
+impl GasConfig {
+    pub fn from_toml(content: &str) -> Result<Self, GasConfigError> {
+        let mut config: GasConfig =
+            toml::from_str(content).map_err(|error| GasConfigError::Parse(error.to_string()))?;
+        // Entry points missing from the file keep their defaults.
+        for (contract, limits) in default_limits() {
+            let configured = config.limits.entry(contract).or_default();
+            for (entry_point, limit) in limits {
+                configured.entry(entry_point).or_insert(limit);
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), GasConfigError> {
+        if self.safety_margin.is_nan() || self.safety_margin < 1.0 {
+            return Err(GasConfigError::Invalid(String::from(
+                "safety_margin must be at least 1",
+            )));
+        }
+        if self.estimate && self.history == 0 {
+            return Err(GasConfigError::Invalid(String::from(
+                "history must be positive when estimating",
+            )));
+        }
+        if self.default_limit.is_nan()
+            || self.default_limit <= 0.0
+            || self.default_limit > self.max_limit
+        {
+            return Err(GasConfigError::Invalid(String::from(
+                "default_limit must be positive and at most max_limit",
+            )));
+        }
+        for (contract, limits) in &self.limits {
+            for (entry_point, limit) in limits {
+                if limit.is_nan() || *limit <= 0.0 || *limit > self.max_limit {
+                    return Err(GasConfigError::Invalid(format!(
+                        "limit of {}.{} must be positive and at most max_limit",
+                        contract, entry_point
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Cost of a single call, as appended to the gas log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GasRecord {
+    pub time: u64,
+    pub contract: String,
+    pub entry_point: String,
+    // Both in motes.
+    pub limit: u64,
+    pub cost: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasLimit {
+    pub motes: u64,
+    // Number of recorded costs the limit is estimated from, 0 for defaults.
+    pub estimated_from: usize,
+}
+
+impl std::fmt::Display for GasLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cspr = self.motes as f64 / MOTES_PER_CSPR;
+        match self.estimated_from {
+            0 => write!(f, "{} CSPR (default)", cspr),
+            count => write!(f, "{} CSPR (estimated from {} recorded calls)", cspr, count),
+        }
+    }
+}
+
+pub struct GasPolicy {
+    config: GasConfig,
+    records: Vec<GasRecord>,
+}
+
+impl GasPolicy {
+    pub fn new(config: GasConfig, records: Vec<GasRecord>) -> Self {
+        GasPolicy { config, records }
+    }
+
+    // Loads the config and the costs recorded so far. Without a config file
+    // the defaults are used and nothing is recorded.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GasConfigError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            odra_cli::log(format!("No gas config at {}, using the defaults.", path.display()));
+            return Ok(Self::new(GasConfig::default(), vec![]));
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| GasConfigError::Io(format!("{}: {}", path.display(), error)))?;
+        let config = GasConfig::from_toml(&content)?;
+        let records = match &config.log {
+            Some(log) => read_records(log)?,
+            None => vec![],
+        };
+        Ok(Self::new(config, records))
+    }
+
+    pub fn limit(&self, contract: &str, entry_point: &str) -> GasLimit {
+        let default = self
+            .config
+            .limits
+            .get(contract)
+            .and_then(|limits| limits.get(entry_point))
+            .copied()
+            .unwrap_or(self.config.default_limit);
+        let default = GasLimit {
+            motes: to_motes(default),
+            estimated_from: 0,
+        };
+        if !self.config.estimate {
+            return default;
+        }
+
+        let costs: Vec<u64> = self
+            .records
+            .iter()
+            .rev()
+            .filter(|record| record.contract == contract && record.entry_point == entry_point)
+            .take(self.config.history)
+            .map(|record| record.cost)
+            .collect();
+        // Without refunds a call costs its whole limit, so estimates above the
+        // default would grow with every call.
+        match costs.iter().max() {
+            Some(max) => GasLimit {
+                motes: (*max as f64 * self.config.safety_margin)
+                    .ceil()
+                    .min(default.motes as f64) as u64,
+                estimated_from: costs.len(),
+            },
+            None => default,
+        }
+    }
+
+    // Sets the gas limit of the entry point and calls it.
+    pub fn call<T>(
+        &mut self,
+        env: &HostEnv,
+        contract: &str,
+        entry_point: &str,
+        call: impl FnOnce() -> T,
+    ) -> T {
+        let limit = self.limit(contract, entry_point);
+        odra_cli::log(format!("Gas limit of {}.{}: {}.", contract, entry_point, limit));
+        env.set_gas(limit.motes);
+        self.measure(env, contract, entry_point, limit.motes, call)
+    }
+
+    // Records the cost of the call as the drop of the caller's balance. Other
+    // transactions of the caller running at the same time skew it.
+    pub fn measure<T>(
+        &mut self,
+        env: &HostEnv,
+        contract: &str,
+        entry_point: &str,
+        limit: u64,
+        call: impl FnOnce() -> T,
+    ) -> T {
+        let Some(log) = self.config.log.clone() else {
+            return call();
+        };
+        let caller = env.caller();
+        let before = env.balance_of(&caller);
+        let result = call();
+        let cost = before.saturating_sub(env.balance_of(&caller));
+        // Calls that didn't reach the network cost nothing.
+        if cost.is_zero() {
+            return result;
+        }
+
+        let record = GasRecord {
+            time: current_timestamp_secs(),
+            contract: contract.to_string(),
+            entry_point: entry_point.to_string(),
+            limit,
+            cost: cost.try_into().unwrap_or(u64::MAX),
+        };
+        odra_cli::log(format!(
+            "Cost of {}.{}: {} CSPR.",
+            contract,
+            entry_point,
+            record.cost as f64 / MOTES_PER_CSPR
+        ));
+        // Recording is best effort, the call is already done.
+        if let Err(error) = append_record(&log, &record) {
+            odra_cli::log(format!("Failed to record gas cost to {}: {}.", log.display(), error));
+        }
+        self.records.push(record);
+        result
+    }
+}
+
+fn to_motes(cspr: f64) -> u64 {
+    (cspr * MOTES_PER_CSPR) as u64
+}
+
+// Reads the gas log, skipping lines that aren't records.
+fn read_records(path: &Path) -> Result<Vec<GasRecord>, GasConfigError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| GasConfigError::Io(format!("{}: {}", path.display(), error)))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn append_record(path: &Path, record: &GasRecord) -> Result<(), String> {
+    let line = serde_json::to_string(record).map_err(|error| error.to_string())?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|error| error.to_string())?;
+    writeln!(file, "{}", line).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(entry_point: &str, cost: u64) -> GasRecord {
+        GasRecord {
+            time: 0,
+            contract: String::from("StyksPriceFeed"),
+            entry_point: entry_point.to_string(),
+            limit: to_motes(2.5),
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_load_example() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/gas.toml");
+        let content = std::fs::read_to_string(path).unwrap();
+        let config = GasConfig::from_toml(&content).unwrap();
+        assert_eq!(config.limits, default_limits());
+        assert!(config.log.is_some());
+    }
+
+    #[test]
+    fn test_config() {
+        // Missing entry points keep their defaults.
+        let config = GasConfig::from_toml(
+            r#"
+            [limits.StyksPriceFeed]
+            set_config = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.limits["StyksPriceFeed"]["set_config"], 5.0);
+        assert_eq!(config.limits["StyksPriceFeed"]["add_to_feed"], 2.5);
+        assert_eq!(config.limits["StyksBlockySupplier"][DEPLOY], 600.0);
+        assert!(!config.estimate);
+
+        let invalid = GasConfig::from_toml("safety_margin = 0.5");
+        assert!(matches!(invalid, Err(GasConfigError::Invalid(_))));
+        let invalid = GasConfig::from_toml("max_limit = 100");
+        assert!(matches!(invalid, Err(GasConfigError::Invalid(_))));
+        let invalid = GasConfig::from_toml("default_limit = 0");
+        assert!(matches!(invalid, Err(GasConfigError::Invalid(_))));
+        let invalid = GasConfig::from_toml("estimate = true\nhistory = 0");
+        assert!(matches!(invalid, Err(GasConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_limit() {
+        let records = vec![
+            record("set_config", 1_000_000_000),
+            record("add_to_feed", 2_000_000_000),
+            record("set_config", 3_000_000_000),
+            record("set_config", 2_000_000_000),
+        ];
+        let mut config = GasConfig {
+            history: 2,
+            safety_margin: 1.2,
+            ..GasConfig::default()
+        };
+
+        // Without estimates the defaults are used.
+        let policy = GasPolicy::new(config.clone(), records.clone());
+        let limit = policy.limit("StyksPriceFeed", "set_config");
+        assert_eq!(limit, GasLimit { motes: 4_000_000_000, estimated_from: 0 });
+        assert_eq!(limit.to_string(), "4 CSPR (default)");
+
+        // The highest of the last two costs, with the margin.
+        config.estimate = true;
+        let policy = GasPolicy::new(config.clone(), records.clone());
+        let limit = policy.limit("StyksPriceFeed", "set_config");
+        assert_eq!(limit, GasLimit { motes: 3_600_000_000, estimated_from: 2 });
+        assert_eq!(limit.to_string(), "3.6 CSPR (estimated from 2 recorded calls)");
+
+        // Without records the default is kept.
+        let limit = policy.limit("StyksBlockySupplier", "set_config");
+        assert_eq!(limit.motes, 3_500_000_000);
+
+        // Estimates are capped at the default.
+        config.safety_margin = 1.5;
+        let policy = GasPolicy::new(config, records);
+        assert_eq!(policy.limit("StyksPriceFeed", "set_config").motes, 4_000_000_000);
+
+        // Entry points without a default get the default limit.
+        let limit = policy.limit("StyksPriceFeed", "unknown");
+        assert_eq!(limit, GasLimit { motes: 5_000_000_000, estimated_from: 0 });
+    }
+
+    #[test]
+    fn test_limit_without_refunds() {
+        // Every call costs its whole limit, the estimate must not grow.
+        let config = GasConfig {
+            estimate: true,
+            ..GasConfig::default()
+        };
+        let mut policy = GasPolicy::new(config, vec![]);
+        for _ in 0..5 {
+            let limit = policy.limit("StyksPriceFeed", "add_to_feed");
+            assert_eq!(limit.motes, 2_500_000_000);
+            policy.records.push(GasRecord {
+                limit: limit.motes,
+                ..record("add_to_feed", limit.motes)
+            });
+        }
+        assert_eq!(policy.limit("StyksPriceFeed", "add_to_feed").estimated_from, 5);
+    }
+
+    #[test]
+    fn test_gas_log() {
+        let path = std::env::temp_dir().join(format!("styks-gas-{}.jsonl", std::process::id()));
+        assert_eq!(read_records(&path), Ok(vec![]));
+
+        append_record(&path, &record("set_config", 1)).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not a record\n")
+            .unwrap();
+        append_record(&path, &record("add_to_feed", 2)).unwrap();
+        assert_eq!(
+            read_records(&path),
+            Ok(vec![record("set_config", 1), record("add_to_feed", 2)])
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
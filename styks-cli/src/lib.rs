@@ -1 +1,11 @@
+pub mod blocky_client;
+pub mod clock;
+pub mod deployment;
+pub mod feed_status;
+pub mod gas;
+pub mod price_source;
+pub mod producer;
+pub mod roles;
+pub mod schedule;
+pub mod verifier;
 pub mod scenarios;
@@ -0,0 +1,325 @@
+//! Sources of the prices reported by the producer.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use styks_blocky_parser::blocky_output::BlockyOutput;
+use styks_core::{Price, PriceFeedId};
+
+use crate::{
+    blocky_client::{BlockyClient, FnCall},
+    producer::BlockySettings,
+};
+
+// Prices are stored with 5 decimals.
+pub const PRICE_SCALE: f64 = 100_000.0;
+
+#[derive(Debug)]
+pub enum PriceReport {
+    // Price posted directly to the feed.
+    Price(Price),
+    // Attested output of the guest program, reported via the supplier.
+    Attested(Box<BlockyOutput>),
+}
+
+pub trait PriceSource {
+    fn name(&self) -> &'static str;
+    fn fetch(&self, price_feed_id: &PriceFeedId) -> Result<PriceReport, String>;
+}
+
+// Source of a feed, as set in the producer config.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum SourceConfig {
+    Blocky {
+        // Overrides the default `fn-call.json` of the `[blocky]` section.
+        #[serde(default)]
+        fn_call: Option<PathBuf>,
+    },
+    Coingecko {
+        coin_id: String,
+        #[serde(default = "default_vs_currency")]
+        vs_currency: String,
+    },
+    Static {
+        price: Price,
+    },
+    File {
+        path: PathBuf,
+    },
+}
+
+fn default_vs_currency() -> String {
+    String::from("usd")
+}
+
+impl SourceConfig {
+    // Attested sources are reported via StyksBlockySupplier.
+    pub fn is_attested(&self) -> bool {
+        matches!(self, SourceConfig::Blocky { .. })
+    }
+
+    // Function call a Blocky source attests, `None` for other sources.
+    pub fn fn_call(&self, blocky: &BlockySettings) -> Option<PathBuf> {
+        match self {
+            SourceConfig::Blocky { fn_call } => {
+                Some(fn_call.clone().unwrap_or_else(|| blocky.fn_call.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn build(&self, blocky: &BlockySettings) -> Result<Box<dyn PriceSource>, String> {
+        let source: Box<dyn PriceSource> = match self {
+            SourceConfig::Blocky { .. } => Box::new(BlockySource {
+                config: blocky.config.clone(),
+                fn_call: self.fn_call(blocky).unwrap_or_default(),
+                output: blocky.output.clone(),
+            }),
+            SourceConfig::Coingecko { coin_id, vs_currency } => Box::new(CoinGeckoSource {
+                client: CoinGeckoClient::new()?,
+                coin_id: coin_id.clone(),
+                vs_currency: vs_currency.clone(),
+            }),
+            SourceConfig::Static { price } => Box::new(StaticSource { price: *price }),
+            SourceConfig::File { path } => Box::new(FileSource { path: path.clone() }),
+        };
+        Ok(source)
+    }
+}
+
+// Attests the guest program in the Blocky enclave. The feed id isn't passed
+// to the guest, so each feed needs its own function call.
+pub struct BlockySource {
+    config: PathBuf,
+    fn_call: PathBuf,
+    output: Option<PathBuf>,
+}
+
+impl PriceSource for BlockySource {
+    fn name(&self) -> &'static str {
+        "blocky"
+    }
+
+    fn fetch(&self, _: &PriceFeedId) -> Result<PriceReport, String> {
+        let client = BlockyClient::load(&self.config).map_err(|e| e.to_string())?;
+        let call = FnCall::load(&self.fn_call).map_err(|e| e.to_string())?;
+        let output = client.attest_fn_call(&call).map_err(|e| e.to_string())?;
+        if let Some(path) = &self.output {
+            save_output(path, &output);
+        }
+        Ok(PriceReport::Attested(Box::new(output)))
+    }
+}
+
+// Saving is best effort, the output is already in memory.
+fn save_output(path: &Path, output: &BlockyOutput) {
+    let result = serde_json::to_string_pretty(output)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+    if let Err(error) = result {
+        odra_cli::log(format!("Failed to save Blocky output to {}: {}.", path.display(), error));
+    }
+}
+
+pub struct CoinGeckoSource {
+    client: CoinGeckoClient,
+    coin_id: String,
+    vs_currency: String,
+}
+
+impl PriceSource for CoinGeckoSource {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn fetch(&self, _: &PriceFeedId) -> Result<PriceReport, String> {
+        let price = self.client.get_price(&self.coin_id, &self.vs_currency)?;
+        to_price(price).map(PriceReport::Price)
+    }
+}
+
+// Always reports the same price, useful on testnets.
+pub struct StaticSource {
+    price: Price,
+}
+
+impl PriceSource for StaticSource {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn fetch(&self, _: &PriceFeedId) -> Result<PriceReport, String> {
+        Ok(PriceReport::Price(self.price))
+    }
+}
+
+// Reads the price, with 5 decimals, from a file kept up to date by another
+// process.
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl PriceSource for FileSource {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn fetch(&self, _: &PriceFeedId) -> Result<PriceReport, String> {
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        content
+            .trim()
+            .parse()
+            .map(PriceReport::Price)
+            .map_err(|e| format!("{}: invalid price: {}", self.path.display(), e))
+    }
+}
+
+fn to_price(price: f64) -> Result<Price, String> {
+    let scaled = (price * PRICE_SCALE).round();
+    if !scaled.is_finite() || scaled <= 0.0 || scaled > Price::MAX as f64 {
+        return Err(format!("Price out of range: {}", price));
+    }
+    Ok(scaled as Price)
+}
+
+pub struct CoinGeckoClient {
+    api_key: String,
+}
+
+impl CoinGeckoClient {
+    pub fn new() -> Result<Self, String> {
+        // Read COINGECKO_PRO_API_KEY key from environment variable.
+        let api_key = std::env::var("COINGECKO_PRO_API_KEY")
+            .map_err(|_| String::from("COINGECKO_PRO_API_KEY environment variable not set"))?;
+        Ok(CoinGeckoClient { api_key })
+    }
+
+    pub fn get_price(&self, coin_id: &str, vs_currency: &str) -> Result<f64, String> {
+        let url = format!(
+            "https://api.coingecko.com/api/v3/simple/price?vs_currencies={}&ids={}",
+            vs_currency, coin_id
+        );
+        let mut response = ureq::get(url)
+            .header("x-cg-demo-api-key", &self.api_key)
+            .call()
+            .map_err(|e| format!("Failed to fetch price: {}", e))?;
+        let body = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        let json: serde_json::Value =
+            serde_json::from_str(&body).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        json[coin_id][vs_currency]
+            .as_f64()
+            .ok_or_else(|| String::from("Price not found in response"))
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+    use super::*;
+
+    // Prices set by the test, per feed. Clones share the prices.
+    #[derive(Default, Clone)]
+    pub struct InMemorySource {
+        prices: Rc<RefCell<BTreeMap<PriceFeedId, Result<Price, String>>>>,
+    }
+
+    impl InMemorySource {
+        pub fn set(&self, price_feed_id: &str, price: Result<Price, String>) {
+            self.prices.borrow_mut().insert(price_feed_id.to_string(), price);
+        }
+    }
+
+    impl PriceSource for InMemorySource {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn fetch(&self, price_feed_id: &PriceFeedId) -> Result<PriceReport, String> {
+            self.prices
+                .borrow()
+                .get(price_feed_id)
+                .cloned()
+                .unwrap_or_else(|| Err(format!("No price for {}", price_feed_id)))
+                .map(PriceReport::Price)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{testing::InMemorySource, *};
+
+    fn price(source: &dyn PriceSource, id: &str) -> Result<Price, String> {
+        match source.fetch(&id.to_string())? {
+            PriceReport::Price(price) => Ok(price),
+            PriceReport::Attested(_) => Err(String::from("attested")),
+        }
+    }
+
+    #[test]
+    fn test_source_config() {
+        let config: std::collections::BTreeMap<String, SourceConfig> = toml::from_str(
+            r#"
+            CSPRUSD = { source = "blocky" }
+            BTCUSD = { source = "coingecko", coin_id = "bitcoin" }
+            TESTUSD = { source = "static", price = 123 }
+            FILEUSD = { source = "file", path = "price.txt" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config["CSPRUSD"], SourceConfig::Blocky { fn_call: None });
+        assert_eq!(
+            config["BTCUSD"],
+            SourceConfig::Coingecko {
+                coin_id: String::from("bitcoin"),
+                vs_currency: String::from("usd")
+            }
+        );
+
+        let blocky = BlockySettings::default();
+        let source = config["TESTUSD"].build(&blocky).unwrap();
+        assert_eq!(source.name(), "static");
+        assert_eq!(price(source.as_ref(), "TESTUSD"), Ok(123));
+        assert_eq!(config["CSPRUSD"].build(&blocky).unwrap().name(), "blocky");
+
+        let unknown = toml::from_str::<SourceConfig>("source = \"binance\"");
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_file_source() {
+        let path = std::env::temp_dir().join(format!("styks-price-{}.txt", std::process::id()));
+        let source = FileSource { path: path.clone() };
+        assert!(price(&source, "CSPRUSD").is_err());
+
+        std::fs::write(&path, "1234\n").unwrap();
+        assert_eq!(price(&source, "CSPRUSD"), Ok(1234));
+        std::fs::write(&path, "12.34").unwrap();
+        assert!(price(&source, "CSPRUSD").is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_source() {
+        let source = InMemorySource::default();
+        assert!(price(&source, "CSPRUSD").is_err());
+        source.set("CSPRUSD", Ok(5));
+        source.set("BTCUSD", Err(String::from("down")));
+        assert_eq!(price(&source, "CSPRUSD"), Ok(5));
+        assert_eq!(price(&source, "BTCUSD"), Err(String::from("down")));
+    }
+
+    #[test]
+    fn test_to_price() {
+        assert_eq!(to_price(0.0123), Ok(1230));
+        assert_eq!(to_price(1.000004), Ok(100000));
+        assert!(to_price(0.0).is_err());
+        assert!(to_price(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,521 @@
+//! Settings, retries and shutdown handling of the price producer daemon.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::Deserialize;
+use styks_blocky_parser::blocky_output::BlockyOutput;
+use styks_core::{Price, PriceFeedId, SourceKey};
+
+use crate::price_source::{PriceReport, PriceSource, SourceConfig};
+
+pub const DEFAULT_PRODUCER_CONFIG: &str = "resources/producer.toml";
+
+#[derive(Debug, PartialEq)]
+pub enum ProducerConfigError {
+    Io(String),
+    Parse(String),
+    InvalidRetry,
+    NoFeeds,
+    MixedSources,
+    SharedFnCall(PriceFeedId, PriceFeedId),
+}
+
+impl std::fmt::Display for ProducerConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProducerConfigError::Io(error) => write!(f, "Failed to read producer config: {}", error),
+            ProducerConfigError::Parse(error) => {
+                write!(f, "Failed to parse producer config: {}", error)
+            }
+            ProducerConfigError::InvalidRetry => {
+                write!(f, "Retry delays must be positive and initial_delay_secs <= max_delay_secs")
+            }
+            ProducerConfigError::NoFeeds => write!(f, "No price sources are configured"),
+            ProducerConfigError::MixedSources => write!(
+                f,
+                "Blocky sources can't be mixed with direct ones, the prices of all feeds are posted in one call"
+            ),
+            ProducerConfigError::SharedFnCall(first, second) => write!(
+                f,
+                "Blocky feeds {} and {} use the same fn_call, each feed needs its own guest call",
+                first, second
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProducerConfigError {}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProducerConfig {
+    // Source of the price of each feed.
+    pub feeds: BTreeMap<PriceFeedId, SourceConfig>,
+    #[serde(default)]
+    pub blocky: BlockySettings,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlockySettings {
+    // Host and auth token of the Blocky service.
+    pub config: PathBuf,
+    // Function call of the guest program.
+    pub fn_call: PathBuf,
+    // If set, the last attested output is saved there for inspection.
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+}
+
+impl Default for BlockySettings {
+    fn default() -> Self {
+        BlockySettings {
+            config: PathBuf::from("blocky-guest/config.toml"),
+            fn_call: PathBuf::from("blocky-guest/fn-call.json"),
+            output: Some(PathBuf::from("blocky-guest/tmp/out.json")),
+        }
+    }
+}
+
+// Exponential backoff between attempts within a single heartbeat window.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RetryPolicy {
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_delay_secs: 10,
+            max_delay_secs: 120,
+        }
+    }
+}
+
+impl ProducerConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ProducerConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| ProducerConfigError::Io(format!("{}: {}", path.display(), error)))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, ProducerConfigError> {
+        let config: ProducerConfig = toml::from_str(content)
+            .map_err(|error| ProducerConfigError::Parse(error.to_string()))?;
+        if config.feeds.is_empty() {
+            return Err(ProducerConfigError::NoFeeds);
+        }
+        let attested = config.feeds.values().filter(|source| source.is_attested()).count();
+        if attested != 0 && attested != config.feeds.len() {
+            return Err(ProducerConfigError::MixedSources);
+        }
+        let mut fn_calls: BTreeMap<PathBuf, &PriceFeedId> = BTreeMap::new();
+        for (id, source) in &config.feeds {
+            let Some(fn_call) = source.fn_call(&config.blocky) else {
+                continue;
+            };
+            if let Some(other) = fn_calls.insert(fn_call, id) {
+                return Err(ProducerConfigError::SharedFnCall(other.clone(), id.clone()));
+            }
+        }
+        let retry = &config.retry;
+        if retry.initial_delay_secs == 0 || retry.initial_delay_secs > retry.max_delay_secs {
+            return Err(ProducerConfigError::InvalidRetry);
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RetryOutcome {
+    Succeeded { attempts: u32 },
+    WindowClosed { attempts: u32, last_error: String },
+    Stopped { attempts: u32, error: String },
+    ShutdownRequested,
+}
+
+// Failure of a single attempt. Permanent failures can't be fixed by
+// retrying in the same window.
+#[derive(Debug, PartialEq)]
+pub enum AttemptError {
+    Retryable(String),
+    Permanent(String),
+}
+
+impl std::fmt::Display for AttemptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttemptError::Retryable(error) | AttemptError::Permanent(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+// Calls `attempt` until it succeeds, fails permanently, the window closes at
+// `deadline` or a shutdown is requested. `sleep` returns false if it was
+// interrupted by a shutdown request.
+pub fn retry_until<N, S, A>(
+    policy: &RetryPolicy,
+    deadline: u64,
+    mut now: N,
+    mut sleep: S,
+    mut attempt: A,
+) -> RetryOutcome
+where
+    N: FnMut() -> u64,
+    S: FnMut(u64) -> bool,
+    A: FnMut(u32) -> Result<(), AttemptError>,
+{
+    let mut delay = policy.initial_delay_secs;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let last_error = match attempt(attempts) {
+            Ok(()) => return RetryOutcome::Succeeded { attempts },
+            Err(AttemptError::Permanent(error)) => return RetryOutcome::Stopped { attempts, error },
+            Err(AttemptError::Retryable(error)) => error,
+        };
+
+        let current_time = now();
+        if current_time >= deadline {
+            return RetryOutcome::WindowClosed { attempts, last_error };
+        }
+        if !sleep(delay.min(deadline - current_time)) {
+            return RetryOutcome::ShutdownRequested;
+        }
+        delay = delay.saturating_mul(2).min(policy.max_delay_secs);
+    }
+}
+
+// Reports of a single heartbeat, split by the entry point they are sent to.
+#[derive(Debug, Default)]
+pub struct Submission {
+    // Prices posted together to StyksPriceFeed, sorted by id.
+    pub direct: Vec<(PriceFeedId, Price)>,
+    // Attested outputs posted together via StyksBlockySupplier, sorted by id.
+    pub attested: Vec<(PriceFeedId, BlockyOutput)>,
+    // Feeds that can't be reported, with the reason.
+    pub failed: Vec<(PriceFeedId, String)>,
+}
+
+// Fetches the prices of the `pending` feeds. `add_to_feed` takes the prices
+// of all `price_feed_ids` at once, so prices are only submitted if they
+// cover every feed.
+pub fn collect_reports(
+    price_feed_ids: &[PriceFeedId],
+    pending: &BTreeSet<PriceFeedId>,
+    sources: &BTreeMap<PriceFeedId, Box<dyn PriceSource>>,
+    mappings: &[(SourceKey, PriceFeedId)],
+) -> Submission {
+    let mut submission = Submission::default();
+    for id in price_feed_ids.iter().filter(|id| pending.contains(*id)) {
+        let report = match sources.get(id) {
+            Some(source) => source.fetch(id),
+            None => Err(String::from("No price source configured")),
+        };
+        match report {
+            Ok(PriceReport::Price(price)) => submission.direct.push((id.clone(), price)),
+            Ok(PriceReport::Attested(output)) => match check_attested_feed(id, &output, mappings) {
+                Ok(()) => submission.attested.push((id.clone(), *output)),
+                Err(error) => submission.failed.push((id.clone(), error)),
+            },
+            Err(error) => submission.failed.push((id.clone(), error)),
+        }
+    }
+
+    let mut all_ids = price_feed_ids.to_vec();
+    all_ids.sort();
+    hold_back_partial(&mut submission.direct, &all_ids, "direct", &mut submission.failed);
+    hold_back_partial(&mut submission.attested, &all_ids, "attested", &mut submission.failed);
+    submission
+}
+
+// The supplier posts an attested price to the feed its source is mapped to,
+// which has to be the feed the output was fetched for.
+fn check_attested_feed(
+    price_feed_id: &PriceFeedId,
+    output: &BlockyOutput,
+    mappings: &[(SourceKey, PriceFeedId)],
+) -> Result<(), String> {
+    let value = output
+        .claims()
+        .map_err(|e| format!("Invalid Blocky claims: {}", e))
+        .and_then(|claims| claims.output().map_err(|e| format!("Invalid Blocky output: {:?}", e)))?;
+    let source_key = SourceKey::new(&value.market, &value.coin_id, &value.currency);
+    match mappings.iter().find(|(key, _)| *key == source_key) {
+        Some((_, id)) if id == price_feed_id => Ok(()),
+        Some((_, id)) => Err(format!(
+            "Attested {} {}/{} is mapped to {}",
+            value.market, value.coin_id, value.currency, id
+        )),
+        None => Err(format!(
+            "No feed is mapped to {} {}/{}",
+            value.market, value.coin_id, value.currency
+        )),
+    }
+}
+
+// Sorts the reports and moves them to `failed` unless they cover `all_ids`.
+fn hold_back_partial<T>(
+    reports: &mut Vec<(PriceFeedId, T)>,
+    all_ids: &[PriceFeedId],
+    kind: &str,
+    failed: &mut Vec<(PriceFeedId, String)>,
+) {
+    reports.sort_by(|a, b| a.0.cmp(&b.0));
+    let ids: Vec<&PriceFeedId> = reports.iter().map(|(id, _)| id).collect();
+    if !reports.is_empty() && !ids.iter().copied().eq(all_ids.iter()) {
+        for (id, _) in reports.drain(..) {
+            let error = format!("Not submitted, {} prices must cover all feeds", kind);
+            failed.push((id, error));
+        }
+    }
+}
+
+static SIGNAL_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_: libc::c_int) {
+    SIGNAL_RECEIVED.store(true, Ordering::SeqCst);
+}
+
+// Shutdown flag shared by the daemon loop and its sleeps.
+#[derive(Clone, Copy)]
+pub struct Shutdown {
+    flag: &'static AtomicBool,
+}
+
+impl Shutdown {
+    pub fn new(flag: &'static AtomicBool) -> Self {
+        Shutdown { flag }
+    }
+
+    // Requests a shutdown on SIGTERM and SIGINT.
+    pub fn on_signals() -> Self {
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        unsafe {
+            libc::signal(libc::SIGTERM, handler);
+            libc::signal(libc::SIGINT, handler);
+        }
+        Shutdown::new(&SIGNAL_RECEIVED)
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    // Sleeps in one second steps, so a shutdown request is noticed quickly.
+    // Returns false if the sleep was cut short.
+    pub fn sleep(&self, secs: u64) -> bool {
+        for _ in 0..secs {
+            if self.is_requested() {
+                return false;
+            }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+        !self.is_requested()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+    use crate::price_source::testing::InMemorySource;
+
+    #[test]
+    fn test_load_example() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/producer.toml");
+        let config = ProducerConfig::load(path).unwrap();
+        assert_eq!(config.feeds["CSPRUSD"], SourceConfig::Blocky { fn_call: None });
+        assert_eq!(config.blocky, BlockySettings::default());
+        assert_eq!(config.retry, RetryPolicy::default());
+
+        // Missing sections fall back to the defaults.
+        let config = ProducerConfig::from_toml("[feeds]\nCSPRUSD = { source = \"static\", price = 1 }");
+        assert_eq!(config.unwrap().retry, RetryPolicy::default());
+        assert_eq!(ProducerConfig::from_toml("[feeds]"), Err(ProducerConfigError::NoFeeds));
+        let config = ProducerConfig::from_toml(
+            "[feeds]\nCSPRUSD = { source = \"blocky\" }\n[retry]\ninitial_delay_secs = 0\nmax_delay_secs = 1",
+        );
+        assert_eq!(config, Err(ProducerConfigError::InvalidRetry));
+        let config = ProducerConfig::from_toml(
+            "[feeds]\nCSPRUSD = { source = \"blocky\" }\nBTCUSD = { source = \"static\", price = 1 }",
+        );
+        assert_eq!(config, Err(ProducerConfigError::MixedSources));
+
+        // Blocky feeds need their own function calls.
+        let config = ProducerConfig::from_toml(
+            "[feeds]\nCSPRUSD = { source = \"blocky\" }\nBTCUSD = { source = \"blocky\" }",
+        );
+        let shared = ProducerConfigError::SharedFnCall(String::from("BTCUSD"), String::from("CSPRUSD"));
+        assert_eq!(config, Err(shared));
+        let config = ProducerConfig::from_toml(
+            "[feeds]\nCSPRUSD = { source = \"blocky\" }\nBTCUSD = { source = \"blocky\", fn_call = \"btc.json\" }",
+        );
+        assert!(config.is_ok());
+    }
+
+    #[test]
+    fn test_retry_until() {
+        let policy = RetryPolicy {
+            initial_delay_secs: 10,
+            max_delay_secs: 25,
+        };
+        let clock = Cell::new(100);
+        let now = || clock.get();
+        let sleeps = RefCell::new(vec![]);
+        let sleep = |secs| {
+            sleeps.borrow_mut().push(secs);
+            clock.set(clock.get() + secs);
+            true
+        };
+
+        // Succeeds on the third attempt.
+        let retryable = |attempt| AttemptError::Retryable(format!("error {}", attempt));
+        let outcome = retry_until(&policy, 200, now, sleep, |attempt| match attempt {
+            3 => Ok(()),
+            _ => Err(retryable(attempt)),
+        });
+        assert_eq!(outcome, RetryOutcome::Succeeded { attempts: 3 });
+        assert_eq!(sleeps.take(), vec![10, 20]);
+
+        // Delays are capped and the last one ends with the window.
+        clock.set(100);
+        let outcome = retry_until(&policy, 170, now, sleep, |attempt| Err(retryable(attempt)));
+        assert_eq!(
+            outcome,
+            RetryOutcome::WindowClosed {
+                attempts: 5,
+                last_error: String::from("error 5")
+            }
+        );
+        assert_eq!(sleeps.take(), vec![10, 20, 25, 15]);
+
+        // A permanent failure stops retrying right away.
+        clock.set(100);
+        let outcome = retry_until(&policy, 200, now, sleep, |attempt| match attempt {
+            2 => Err(AttemptError::Permanent(String::from("round committed"))),
+            _ => Err(retryable(attempt)),
+        });
+        assert_eq!(
+            outcome,
+            RetryOutcome::Stopped {
+                attempts: 2,
+                error: String::from("round committed")
+            }
+        );
+        assert_eq!(sleeps.take(), vec![10]);
+
+        // An interrupted sleep stops retrying.
+        let outcome = retry_until(&policy, 1000, now, |_| false, |_| Err(retryable(1)));
+        assert_eq!(outcome, RetryOutcome::ShutdownRequested);
+    }
+
+    fn ids(ids: &[&str]) -> Vec<PriceFeedId> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_collect_reports() {
+        let source = InMemorySource::default();
+        source.set("CSPRUSD", Ok(2));
+        source.set("BTCUSD", Ok(1));
+        let sources = [("CSPRUSD", "memory"), ("BTCUSD", "memory"), ("ETHUSD", "attested")]
+            .into_iter()
+            .map(|(id, kind)| {
+                let source: Box<dyn PriceSource> = match kind {
+                    "memory" => Box::new(source.clone()),
+                    _ => Box::new(AttestedSource),
+                };
+                (id.to_string(), source)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        // The test output attests the Gate CSPR/USD price.
+        let mapping = |id: &str| vec![(SourceKey::new("Gate", "CSPR", "USD"), id.to_string())];
+
+        // Direct prices are sorted.
+        let feeds = ids(&["CSPRUSD", "BTCUSD"]);
+        let pending = feeds.iter().cloned().collect();
+        let submission = collect_reports(&feeds, &pending, &sources, &[]);
+        assert_eq!(
+            submission.direct,
+            vec![(String::from("BTCUSD"), 1), (String::from("CSPRUSD"), 2)]
+        );
+        assert!(submission.failed.is_empty());
+
+        // A missing price holds back the other direct prices.
+        source.set("BTCUSD", Err(String::from("down")));
+        let submission = collect_reports(&feeds, &pending, &sources, &[]);
+        assert!(submission.direct.is_empty());
+        let failed: Vec<&str> = submission.failed.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(failed, vec!["BTCUSD", "CSPRUSD"]);
+        assert_eq!(submission.failed[0].1, "down");
+
+        // An attested output is submitted for the feed its source is mapped to.
+        let feeds = ids(&["ETHUSD"]);
+        let pending = feeds.iter().cloned().collect();
+        let submission = collect_reports(&feeds, &pending, &sources, &mapping("ETHUSD"));
+        let attested: Vec<&str> = submission.attested.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(attested, vec!["ETHUSD"]);
+        assert!(submission.failed.is_empty());
+
+        // Outputs of other feeds' sources fail.
+        let submission = collect_reports(&feeds, &pending, &sources, &mapping("CSPRUSD"));
+        assert!(submission.attested.is_empty());
+        assert_eq!(
+            submission.failed,
+            vec![(String::from("ETHUSD"), String::from("Attested Gate CSPR/USD is mapped to CSPRUSD"))]
+        );
+        let submission = collect_reports(&feeds, &pending, &sources, &[]);
+        assert_eq!(submission.failed[0].1, "No feed is mapped to Gate CSPR/USD");
+
+        // A missing attested output holds back the others, only pending
+        // feeds are fetched and feeds without a source fail.
+        let feeds = ids(&["ETHUSD", "CSPRUSD", "DOGEUSD"]);
+        let pending = ids(&["ETHUSD", "DOGEUSD"]).into_iter().collect();
+        let submission = collect_reports(&feeds, &pending, &sources, &mapping("ETHUSD"));
+        assert!(submission.direct.is_empty());
+        assert!(submission.attested.is_empty());
+        assert_eq!(
+            submission.failed,
+            vec![
+                (String::from("DOGEUSD"), String::from("No price source configured")),
+                (
+                    String::from("ETHUSD"),
+                    String::from("Not submitted, attested prices must cover all feeds")
+                ),
+            ]
+        );
+    }
+
+    struct AttestedSource;
+
+    impl PriceSource for AttestedSource {
+        fn name(&self) -> &'static str {
+            "attested"
+        }
+
+        fn fetch(&self, _: &PriceFeedId) -> Result<PriceReport, String> {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/test/2_out.json");
+            let output = BlockyOutput::try_from_file(path).map_err(|e| e.to_string())?;
+            Ok(PriceReport::Attested(Box::new(output)))
+        }
+    }
+
+    #[test]
+    fn test_shutdown() {
+        static FLAG: AtomicBool = AtomicBool::new(false);
+        let shutdown = Shutdown::new(&FLAG);
+        assert!(shutdown.sleep(0));
+        FLAG.store(true, Ordering::SeqCst);
+        assert!(shutdown.is_requested());
+        assert!(!shutdown.sleep(5));
+    }
+}
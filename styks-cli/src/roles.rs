@@ -0,0 +1,287 @@
+//! Declarative roles manifest of the Styks contracts.
+
+use std::path::Path;
+
+use odra::prelude::Address;
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
+use serde::Deserialize;
+use styks_contracts::{
+    styks_blocky_supplier::StyksBlockySupplerRole, styks_price_feed::StyksPriceFeedRole,
+};
+
+pub const DEFAULT_ROLES_MANIFEST: &str = "resources/roles.toml";
+
+// Manifest entry that stands for the account running the CLI.
+pub const CALLER: &str = "caller";
+
+#[derive(Debug, PartialEq)]
+pub enum RolesError {
+    Io(String),
+    Parse(String),
+    UnknownAddress(String),
+    LastAdmin(&'static str),
+}
+
+impl std::fmt::Display for RolesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RolesError::Io(error) => write!(f, "Failed to read roles manifest: {}", error),
+            RolesError::Parse(error) => write!(f, "Failed to parse roles manifest: {}", error),
+            RolesError::UnknownAddress(entry) => write!(f, "Unknown address or contract: {}", entry),
+            RolesError::LastAdmin(contract) => {
+                write!(f, "Refusing to revoke the last Admin of {}", contract)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RolesError {}
+
+// Who should hold which role. Entries are account or contract hashes, names
+// of deployed contracts or `caller`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RolesManifest {
+    pub price_feed: PriceFeedRoles,
+    pub supplier: SupplierRoles,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PriceFeedRoles {
+    pub admin: Vec<String>,
+    #[serde(default)]
+    pub config_manager: Vec<String>,
+    #[serde(default)]
+    pub price_supplier: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SupplierRoles {
+    pub admin: Vec<String>,
+    #[serde(default)]
+    pub config_manager: Vec<String>,
+    #[serde(default)]
+    pub price_producer: Vec<String>,
+}
+
+// Desired and current holders of a single role.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleState {
+    pub name: String,
+    pub role: Role,
+    pub desired: Vec<Address>,
+    pub current: Vec<Address>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoleChange {
+    Grant { name: String, role: Role, address: Address },
+    Revoke { name: String, role: Role, address: Address },
+}
+
+impl std::fmt::Display for RoleChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoleChange::Grant { name, address, .. } => write!(f, "grant {} to {:?}", name, address),
+            RoleChange::Revoke { name, address, .. } => {
+                write!(f, "revoke {} from {:?}", name, address)
+            }
+        }
+    }
+}
+
+impl RolesManifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RolesError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| RolesError::Io(format!("{}: {}", path.display(), error)))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, RolesError> {
+        toml::from_str(content).map_err(|error| RolesError::Parse(error.to_string()))
+    }
+}
+
+impl PriceFeedRoles {
+    // Desired holders per role, with the entries resolved to addresses.
+    pub fn desired<F>(&self, resolve: F) -> Result<Vec<(String, Role, Vec<Address>)>, RolesError>
+    where
+        F: Fn(&str) -> Option<Address>,
+    {
+        [
+            (StyksPriceFeedRole::Admin, &self.admin),
+            (StyksPriceFeedRole::ConfigManager, &self.config_manager),
+            (StyksPriceFeedRole::PriceSupplier, &self.price_supplier),
+        ]
+        .into_iter()
+        .map(|(role, entries)| {
+            let holders = resolve_all(entries, &resolve)?;
+            Ok((format!("{:?}", role), role.role_id(), holders))
+        })
+        .collect()
+    }
+}
+
+impl SupplierRoles {
+    pub fn desired<F>(&self, resolve: F) -> Result<Vec<(String, Role, Vec<Address>)>, RolesError>
+    where
+        F: Fn(&str) -> Option<Address>,
+    {
+        [
+            (StyksBlockySupplerRole::Admin, &self.admin),
+            (StyksBlockySupplerRole::ConfigManager, &self.config_manager),
+            (StyksBlockySupplerRole::PriceProducer, &self.price_producer),
+        ]
+        .into_iter()
+        .map(|(role, entries)| {
+            let holders = resolve_all(entries, &resolve)?;
+            Ok((format!("{:?}", role), role.role_id(), holders))
+        })
+        .collect()
+    }
+}
+
+fn resolve_all<F>(entries: &[String], resolve: &F) -> Result<Vec<Address>, RolesError>
+where
+    F: Fn(&str) -> Option<Address>,
+{
+    let mut addresses = Vec::new();
+    for entry in entries {
+        let address = resolve(entry).ok_or_else(|| RolesError::UnknownAddress(entry.clone()))?;
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    Ok(addresses)
+}
+
+// Computes the grants and revocations that turn the current holders into the
+// desired ones. Grants go first and the caller's own Admin role is revoked
+// last, so the caller keeps the rights to finish the plan.
+pub fn plan_role_changes(
+    contract: &'static str,
+    roles: &[RoleState],
+    caller: &Address,
+) -> Result<Vec<RoleChange>, RolesError> {
+    // Never leave the contract without an Admin.
+    let no_admin = roles
+        .iter()
+        .any(|state| state.role == DEFAULT_ADMIN_ROLE && state.desired.is_empty());
+    if no_admin {
+        return Err(RolesError::LastAdmin(contract));
+    }
+
+    let mut grants = Vec::new();
+    let mut revokes = Vec::new();
+    for state in roles {
+        for address in state.desired.iter().filter(|a| !state.current.contains(a)) {
+            grants.push(RoleChange::Grant {
+                name: state.name.clone(),
+                role: state.role,
+                address: *address,
+            });
+        }
+        for address in state.current.iter().filter(|a| !state.desired.contains(a)) {
+            let order = match (state.role == DEFAULT_ADMIN_ROLE, address == caller) {
+                (false, _) => 0,
+                (true, false) => 1,
+                (true, true) => 2,
+            };
+            let change = RoleChange::Revoke {
+                name: state.name.clone(),
+                role: state.role,
+                address: *address,
+            };
+            revokes.push((order, change));
+        }
+    }
+    revokes.sort_by_key(|(order, _)| *order);
+    grants.extend(revokes.into_iter().map(|(_, change)| change));
+    Ok(grants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(n: u8) -> Address {
+        format!("account-hash-{}", hex::encode([n; 32])).parse().unwrap()
+    }
+
+    fn state(role: Role, desired: &[Address], current: &[Address]) -> RoleState {
+        RoleState {
+            name: format!("Role{}", role[0]),
+            role,
+            desired: desired.to_vec(),
+            current: current.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_load_example() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/roles.toml");
+        let manifest = RolesManifest::load(path).unwrap();
+        let caller = account(1);
+        let supplier = Address::new(
+            "hash-fe8b1829844276b21e8d577d525808ed36cc9e12c06b5acfb897204e5b49c5a9",
+        )
+        .unwrap();
+        let resolve = |entry: &str| match entry {
+            CALLER => Some(caller),
+            "StyksBlockySupplier" => Some(supplier),
+            _ => entry.parse().ok(),
+        };
+
+        let desired = manifest.price_feed.desired(resolve).unwrap();
+        assert_eq!(desired[0], (String::from("Admin"), DEFAULT_ADMIN_ROLE, vec![caller]));
+        assert_eq!(desired[2].0, "PriceSupplier");
+        assert!(desired[2].2.contains(&supplier));
+        let desired = manifest.supplier.desired(resolve).unwrap();
+        assert_eq!(desired[1].0, "ConfigManager");
+
+        // Unknown entries are rejected.
+        let roles = PriceFeedRoles {
+            admin: vec![String::from("Unknown")],
+            config_manager: vec![],
+            price_supplier: vec![],
+        };
+        assert_eq!(
+            roles.desired(resolve),
+            Err(RolesError::UnknownAddress(String::from("Unknown")))
+        );
+    }
+
+    #[test]
+    fn test_plan_role_changes() {
+        let (caller, a, b) = (account(1), account(2), account(3));
+        let manager = [1u8; 32];
+        let roles = [
+            state(DEFAULT_ADMIN_ROLE, &[a], &[caller, b]),
+            state(manager, &[a, b], &[b, caller]),
+        ];
+        let plan = plan_role_changes("Feed", &roles, &caller).unwrap();
+        let plan: Vec<String> = plan.iter().map(ToString::to_string).collect();
+        let name = |n: &str| format!("Role{}", n);
+        assert_eq!(
+            plan,
+            vec![
+                format!("grant {} to {:?}", name("0"), a),
+                format!("grant {} to {:?}", name("1"), a),
+                format!("revoke {} from {:?}", name("1"), caller),
+                format!("revoke {} from {:?}", name("0"), b),
+                format!("revoke {} from {:?}", name("0"), caller),
+            ]
+        );
+
+        // Nothing to do.
+        let roles = [state(DEFAULT_ADMIN_ROLE, &[caller], &[caller])];
+        assert_eq!(plan_role_changes("Feed", &roles, &caller), Ok(vec![]));
+
+        // The last Admin stays.
+        let roles = [state(DEFAULT_ADMIN_ROLE, &[], &[caller])];
+        assert_eq!(
+            plan_role_changes("Feed", &roles, &caller),
+            Err(RolesError::LastAdmin("Feed"))
+        );
+    }
+}
@@ -1,9 +1,13 @@
+mod plan_schedule;
 mod set_config;
 mod set_permissions;
-// mod list_feed;
+mod show_feed;
 mod update_price;
+mod verify_report;
 
+pub use plan_schedule::PlanSchedule;
 pub use set_config::SetConfig;
 pub use set_permissions::SetPermissions;
-// pub use list_feed::ListFeed;
+pub use show_feed::ShowFeed;
 pub use update_price::UpdatePrice;
+pub use verify_report::VerifyReport;
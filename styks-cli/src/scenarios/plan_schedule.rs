@@ -0,0 +1,119 @@
+use odra::{host::HostEnv, schema::casper_contract_schema::NamedCLType};
+use odra_cli::{
+    scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, DeployedContractsContainer
+};
+
+use crate::{
+    clock::current_timestamp_secs,
+    deployment::{DeploymentConfig, DEFAULT_DEPLOYMENT_CONFIG},
+    schedule::plan_schedule,
+};
+
+// Number of windows listed by default.
+const DEFAULT_WINDOW_COUNT: u64 = 5;
+
+pub struct PlanSchedule;
+
+impl ScenarioMetadata for PlanSchedule {
+    const NAME: &'static str = "PlanSchedule";
+    const DESCRIPTION: &'static str =
+        "Validates a candidate StyksPriceFeed config and shows its heartbeat and TWAP schedule.";
+}
+
+impl Scenario for PlanSchedule {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![
+            CommandArg::new(
+                "config",
+                "Path to the TOML or JSON deployment config. Defaults to resources/deployment.toml.",
+                NamedCLType::String,
+            ),
+            CommandArg::new(
+                "heartbeat_interval",
+                "Overrides the heartbeat interval in seconds.",
+                NamedCLType::U64,
+            ),
+            CommandArg::new(
+                "heartbeat_tolerance",
+                "Overrides the heartbeat tolerance in seconds.",
+                NamedCLType::U64,
+            ),
+            CommandArg::new(
+                "twap_window",
+                "Overrides the number of values in the TWAP.",
+                NamedCLType::U64,
+            ),
+            CommandArg::new(
+                "twap_tolerance",
+                "Overrides the number of values the TWAP can miss.",
+                NamedCLType::U64,
+            ),
+            CommandArg::new(
+                "time",
+                "Time in seconds the schedule starts from. Defaults to now.",
+                NamedCLType::U64,
+            ),
+            CommandArg::new(
+                "count",
+                "Number of heartbeat windows to list. Defaults to 5.",
+                NamedCLType::U64,
+            ),
+        ]
+    }
+
+    fn run(
+        &self,
+        _env: &HostEnv,
+        _container: &DeployedContractsContainer,
+        args: Args,
+    ) -> core::result::Result<(), Error> {
+        let path = args
+            .get_single::<String>("config")
+            .unwrap_or_else(|_| String::from(DEFAULT_DEPLOYMENT_CONFIG));
+        odra_cli::log(format!("Loading deployment config from {}.", path));
+        let deployment = DeploymentConfig::load(&path).map_err(|error| Error::OdraError {
+            message: error.to_string(),
+        })?;
+
+        let mut config = deployment.feed_config();
+        if let Ok(interval) = args.get_single::<u64>("heartbeat_interval") {
+            config.heartbeat_interval = interval;
+        }
+        if let Ok(tolerance) = args.get_single::<u64>("heartbeat_tolerance") {
+            config.heartbeat_tolerance = tolerance;
+        }
+        if let Ok(window) = args.get_single::<u64>("twap_window") {
+            config.twap_window = to_u32("twap_window", window)?;
+        }
+        if let Ok(tolerance) = args.get_single::<u64>("twap_tolerance") {
+            config.twap_tolerance = to_u32("twap_tolerance", tolerance)?;
+        }
+        let time = args
+            .get_single::<u64>("time")
+            .unwrap_or_else(|_| current_timestamp_secs());
+        let count = args.get_single::<u64>("count").unwrap_or(DEFAULT_WINDOW_COUNT);
+
+        odra_cli::log(format!(
+            "Config: heartbeat every {}s ±{}s, TWAP of {} values with up to {} missing.",
+            config.heartbeat_interval,
+            config.heartbeat_tolerance,
+            config.twap_window,
+            config.twap_tolerance
+        ));
+        let plan = plan_schedule(&config, time, count as usize).map_err(|reason| {
+            Error::OdraError {
+                message: format!("Invalid config: {}", reason),
+            }
+        })?;
+        for line in plan.to_lines() {
+            odra_cli::log(line);
+        }
+        Ok(())
+    }
+}
+
+fn to_u32(name: &str, value: u64) -> Result<u32, Error> {
+    u32::try_from(value).map_err(|_| Error::OdraError {
+        message: format!("{} is too large: {}", name, value),
+    })
+}
@@ -1,54 +1,75 @@
-use odra::{casper_types::{bytesrepr::Bytes, Block}, contract_def::HasIdent, host::HostEnv};
+use odra::{contract_def::HasIdent, host::HostEnv, schema::casper_contract_schema::NamedCLType};
 use odra_cli::{
-    cspr, scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
+    scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
+};
+use styks_contracts::{styks_blocky_supplier::StyksBlockySupplier, styks_price_feed::StyksPriceFeed};
+
+use crate::{
+    deployment::{
+        diff_feed_config, diff_mappings, diff_supplier_config, DeploymentConfig, FieldChange,
+        DEFAULT_DEPLOYMENT_CONFIG,
+    },
+    gas::{GasPolicy, DEFAULT_GAS_CONFIG},
 };
-use styks_blocky_parser::{block_output_for_tests, blocky_claims::BlockyClaims, wasm_hash_for_tests};
-use styks_contracts::{styks_blocky_supplier::{StyksBlockySupplerConfig, StyksBlockySupplier}, styks_price_feed::{StyksPriceFeed, StyksPriceFeedConfig}};
 
 pub struct SetConfig;
 
 impl ScenarioMetadata for SetConfig {
     const NAME: &'static str = "SetConfig";
-    const DESCRIPTION: &'static str = "Sets the configuration for the StyksPriceFeed contract.";
+    const DESCRIPTION: &'static str =
+        "Applies the deployment config file to the StyksPriceFeed and StyksBlockySupplier contracts.";
 }
 
 impl Scenario for SetConfig {
-    fn args(&self) -> Vec<CommandArg> { vec![] }
+    fn args(&self) -> Vec<CommandArg> {
+        vec![CommandArg::new(
+            "config",
+            "Path to the TOML or JSON deployment config. Defaults to resources/deployment.toml.",
+            NamedCLType::String,
+        )]
+    }
 
     fn run(
         &self,
         env: &HostEnv,
         container: &DeployedContractsContainer,
-        _args: Args,
+        args: Args,
     ) -> Result<(), Error> {
-        self.configure_feed(env, container)?;
-        self.configure_supplier(env, container)?;
+        let path = args
+            .get_single::<String>("config")
+            .unwrap_or_else(|_| String::from(DEFAULT_DEPLOYMENT_CONFIG));
+        odra_cli::log(format!("Loading deployment config from {}.", path));
+        let config = DeploymentConfig::load(&path).map_err(|error| Error::OdraError {
+            message: error.to_string(),
+        })?;
+
+        let mut gas = GasPolicy::load(DEFAULT_GAS_CONFIG).map_err(|error| Error::OdraError {
+            message: error.to_string(),
+        })?;
+
+        self.configure_feed(env, container, &config, &mut gas)?;
+        self.configure_supplier(env, container, &config, &mut gas)?;
         Ok(())
     }
 }
 
 impl SetConfig {
-    fn configure_feed(&self, env: &HostEnv, container: &DeployedContractsContainer) -> Result<(), Error> {
-        // Configuring the StyksPriceFeed contract.
-        odra_cli::log("Setting configuration for StyksPriceFeed contract.");
-        let mut feed = container.contract_ref::<StyksPriceFeed>(&env)?;
-        let config = StyksPriceFeedConfig {
-            heartbeat_interval: 30 * 60,
-            heartbeat_tolerance: 60,
-            twap_window: 3,
-            twap_tolerance: 1,
-            price_feed_ids: vec![String::from("CSPRUSD")],
-        };
+    fn configure_feed(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        config: &DeploymentConfig,
+        gas: &mut GasPolicy,
+    ) -> Result<(), Error> {
+        let mut feed = container.contract_ref::<StyksPriceFeed>(env)?;
+        let desired = config.feed_config();
+        let current = feed.get_config_or_none();
 
-        if let Some(current_config) = feed.get_config_or_none() {
-            if current_config == config {
-                odra_cli::log("Configuration is already set to the desired values.");
-                return Ok(());
-            }    
+        let changes = diff_feed_config(current.as_ref(), &desired);
+        if !log_changes("StyksPriceFeed", &changes) {
+            return Ok(());
         }
-        odra_cli::log("Current configuration does not match the desired values.");
-        env.set_gas(cspr!(4));
-        feed.set_config(config);
+        gas.call(env, "StyksPriceFeed", "set_config", || feed.set_config(desired));
         odra_cli::log("Configuration set successfully for StyksPriceFeed contract.");
         Ok(())
     }
@@ -57,42 +78,55 @@ impl SetConfig {
         &self,
         env: &HostEnv,
         container: &DeployedContractsContainer,
+        config: &DeploymentConfig,
+        gas: &mut GasPolicy,
     ) -> Result<(), Error> {
-        // Configuring the StyksBlockySupplier contract.
-        odra_cli::log("Setting configuration for StyksBlockySupplier contract.");
-        let mut supplier = container.contract_ref::<StyksBlockySupplier>(&env)?;
+        let mut supplier = container.contract_ref::<StyksBlockySupplier>(env)?;
         let feed_addr = container.address_by_name(&StyksPriceFeed::ident()).unwrap();
+        let desired = config
+            .supplier_config(feed_addr)
+            .map_err(|error| Error::OdraError { message: error.to_string() })?;
+        let current = supplier.get_config_or_none();
+        let mappings = diff_mappings(&supplier.get_feed_mappings(), &config.mappings());
+        if mappings.is_empty() {
+            odra_cli::log("StyksBlockySupplier feed mappings are already set to the desired values.");
+        }
 
-        // Load blocky configuration.
-        let wasm_hash = wasm_hash_for_tests();
-        let blocky_output = block_output_for_tests();
-        let public_key = blocky_output.public_key_bytes();
+        // Stale mappings go first, so the new config is validated without them.
+        for (source_key, price_feed_id) in mappings.removed {
+            odra_cli::log(format!("Removing feed mapping {:?} -> {}.", source_key, price_feed_id));
+            gas.call(env, "StyksBlockySupplier", "remove_feed_mapping", || {
+                supplier.remove_feed_mapping(source_key)
+            });
+        }
 
-        let supplier_config = StyksBlockySupplerConfig {
-            wasm_hash,
-            public_key: Bytes::from(public_key),
-            coingecko_feed_ids: vec![
-                (String::from("Gate_CSPR_USD"), String::from("CSPRUSD"))
-            ],
-            price_feed_address: feed_addr,
-            timestamp_tolerance: 20 * 60 // 20 minutes tolerance
-        };
-        
-        if let Some(current_config) = supplier.get_config_or_none() {
-            if current_config == supplier_config {
-                odra_cli::log("StyksBlockySupplier configuration is already set to the desired values.");
-                return Ok(());
-            } else {
-                odra_cli::log("Current configuration does not match the desired values.");
-            }
-        } else {
-            odra_cli::log("StyksBlockySupplier configuration is not set, setting it now.");
+        let changes = diff_supplier_config(current.as_ref(), &desired);
+        if log_changes("StyksBlockySupplier", &changes) {
+            gas.call(env, "StyksBlockySupplier", "set_config", || {
+                supplier.set_config(desired)
+            });
+            odra_cli::log("Configuration set successfully for StyksBlockySupplier contract.");
         }
 
-        env.set_gas(cspr!(3.5));
-        supplier.set_config(supplier_config);
-        odra_cli::log("Configuration set successfully for StyksBlockySupplier contract.");
-        
+        for (source_key, price_feed_id) in mappings.added {
+            odra_cli::log(format!("Adding feed mapping {:?} -> {}.", source_key, price_feed_id));
+            gas.call(env, "StyksBlockySupplier", "add_feed_mapping", || {
+                supplier.add_feed_mapping(source_key, price_feed_id)
+            });
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// Prints the changes and returns true if there are any.
+fn log_changes(contract: &str, changes: &[FieldChange]) -> bool {
+    if changes.is_empty() {
+        odra_cli::log(format!("{} configuration is already set to the desired values.", contract));
+        return false;
+    }
+    odra_cli::log(format!("{} configuration changes:", contract));
+    for change in changes {
+        odra_cli::log(format!("  {}", change));
+    }
+    true
+}
@@ -1,82 +1,195 @@
 use odra::prelude::*;
-use odra::host::HostEnv;
+use odra::{
+    casper_types::bytesrepr::FromBytes, host::{HostEnv, HostRef},
+    schema::casper_contract_schema::NamedCLType,
+};
 use odra_cli::{
-    cspr, scenario::{Args, Error, Scenario, ScenarioMetadata}, ContractProvider, DeployedContractsContainer
+    scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
+};
+use odra_modules::access::{events::RoleGranted, Role};
+use odra::casper_event_standard::EventInstance;
+use styks_contracts::{styks_blocky_supplier::{StyksBlockySupplier, StyksBlockySupplierHostRef}, styks_price_feed::{StyksPriceFeed, StyksPriceFeedHostRef}};
+
+use crate::{
+    gas::{GasPolicy, DEFAULT_GAS_CONFIG},
+    roles::{
+        plan_role_changes, RoleChange, RoleState, RolesManifest, CALLER, DEFAULT_ROLES_MANIFEST,
+    },
 };
-use styks_contracts::{styks_blocky_supplier::{StyksBlockySupplerRole, StyksBlockySupplier, StyksBlockySupplierHostRef}, styks_price_feed::{StyksPriceFeed, StyksPriceFeedHostRef, StyksPriceFeedRole}};
 
 pub struct SetPermissions;
 
 impl ScenarioMetadata for SetPermissions {
     const NAME: &'static str = "SetPermissions";
-    const DESCRIPTION: &'static str = "Setup testnet permissions.";
+    const DESCRIPTION: &'static str =
+        "Grants and revokes roles to match the roles manifest.";
 }
 
 impl Scenario for SetPermissions {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![
+            CommandArg::new(
+                "manifest",
+                "Path to the roles manifest. Defaults to resources/roles.toml.",
+                NamedCLType::String,
+            ),
+            CommandArg::new(
+                "dry_run",
+                "Only print the changes, without sending them.",
+                NamedCLType::Bool,
+            ),
+        ]
+    }
+
     fn run(
         &self,
         env: &HostEnv,
         container: &DeployedContractsContainer,
-        _args: Args,
+        args: Args,
     ) -> core::result::Result<(), Error> {
-        let mut feed = container.contract_ref::<StyksPriceFeed>(&env)?;
-        let mut supplier = container.contract_ref::<StyksBlockySupplier>(&env)?;
-        let deployer = env.caller();
-
-        // Grant all Config roles to the deployer.
-        odra_cli::log(format!("Setting permissions for address: {:?}", deployer));
-        set_role_feed(&mut feed, &StyksPriceFeedRole::ConfigManager, &deployer, env)?;
-        set_role_supplier(&mut supplier, &StyksBlockySupplerRole::ConfigManager, &deployer, env)?;
-
-        // Grant PriceSupplier role to the account installed on the server.
-        let address = "account-hash-915691433d2c86c6145e46e3c5f3d266d87be6448de5dc8a4c4e710384372916";
-        let address = Address::new(address).unwrap();
-        odra_cli::log(format!("Setting permissions for address: {:?}", address));
-        set_role_feed(&mut feed, &StyksPriceFeedRole::PriceSupplier, &address, env)?;
-
-        // Grant PriceSupplier role to the StyksBlockySupplier in StyksPriceFeed.
-        odra_cli::log("Setting permissions for StyksBlockySupplier contract.");
-        set_role_feed(
-            &mut feed,
-            &StyksPriceFeedRole::PriceSupplier,
-            &supplier.address(),
-            env,
-        )?;
-        
+        let path = args
+            .get_single::<String>("manifest")
+            .unwrap_or_else(|_| String::from(DEFAULT_ROLES_MANIFEST));
+        let dry_run = args.get_single::<bool>("dry_run").unwrap_or(false);
+        odra_cli::log(format!("Loading roles manifest from {}.", path));
+        let manifest = RolesManifest::load(&path).map_err(to_error)?;
+
+        let mut feed = container.contract_ref::<StyksPriceFeed>(env)?;
+        let mut supplier = container.contract_ref::<StyksBlockySupplier>(env)?;
+        let caller = env.caller();
+        let resolve = |entry: &str| match entry {
+            CALLER => Some(caller),
+            _ => container
+                .address_by_name(entry)
+                .or_else(|| entry.parse::<Address>().ok()),
+        };
+
+        // Plan both contracts before changing anything.
+        let desired = manifest.price_feed.desired(resolve).map_err(to_error)?;
+        let feed_plan = plan(env, &feed, desired, &caller)?;
+        let desired = manifest.supplier.desired(resolve).map_err(to_error)?;
+        let supplier_plan = plan(env, &supplier, desired, &caller)?;
+
+        let mut gas = GasPolicy::load(DEFAULT_GAS_CONFIG).map_err(to_error)?;
+        apply(env, &mut gas, &mut feed, &feed_plan, dry_run);
+        apply(env, &mut gas, &mut supplier, &supplier_plan, dry_run);
         Ok(())
     }
 }
 
-fn set_role_feed(
-    contract: &mut StyksPriceFeedHostRef,
-    role: &StyksPriceFeedRole,
-    address: &Address,
+// Access control entry points shared by the contracts.
+trait AccessControlled: HostRef {
+    const NAME: &'static str;
+    fn has_role(&self, role: &Role, address: &Address) -> bool;
+    fn grant_role(&mut self, role: &Role, address: &Address);
+    fn revoke_role(&mut self, role: &Role, address: &Address);
+}
+
+impl AccessControlled for StyksPriceFeedHostRef {
+    const NAME: &'static str = "StyksPriceFeed";
+
+    fn has_role(&self, role: &Role, address: &Address) -> bool {
+        StyksPriceFeedHostRef::has_role(self, role, address)
+    }
+
+    fn grant_role(&mut self, role: &Role, address: &Address) {
+        StyksPriceFeedHostRef::grant_role(self, role, address)
+    }
+
+    fn revoke_role(&mut self, role: &Role, address: &Address) {
+        StyksPriceFeedHostRef::revoke_role(self, role, address)
+    }
+}
+
+impl AccessControlled for StyksBlockySupplierHostRef {
+    const NAME: &'static str = "StyksBlockySupplier";
+
+    fn has_role(&self, role: &Role, address: &Address) -> bool {
+        StyksBlockySupplierHostRef::has_role(self, role, address)
+    }
+
+    fn grant_role(&mut self, role: &Role, address: &Address) {
+        StyksBlockySupplierHostRef::grant_role(self, role, address)
+    }
+
+    fn revoke_role(&mut self, role: &Role, address: &Address) {
+        StyksBlockySupplierHostRef::revoke_role(self, role, address)
+    }
+}
+
+fn plan<C: AccessControlled>(
     env: &HostEnv,
-) -> Result<(), Error> {
-
-    if contract.has_role(&role.role_id(), address) {
-        odra_cli::log(format!("Already has role: {:?} in StyksPriceFeed", role));
-    } else {
-        odra_cli::log(format!("Granting role: {:?} in StyksPriceFeed", role));
-        env.set_gas(cspr!(2.5));
-        contract.grant_role(&role.role_id(), address);
+    contract: &C,
+    desired: Vec<(String, Role, Vec<Address>)>,
+    caller: &Address,
+) -> Result<Vec<RoleChange>, Error> {
+    // Access control can't list role holders, so every address that was ever
+    // granted a role is checked, together with the desired ones.
+    let mut candidates = granted_addresses(env, &contract.address());
+    for (_, _, holders) in &desired {
+        candidates.extend(holders.iter().copied());
     }
-    Ok(())
+    candidates.sort();
+    candidates.dedup();
+
+    let roles: Vec<RoleState> = desired
+        .into_iter()
+        .map(|(name, role, holders)| RoleState {
+            current: candidates
+                .iter()
+                .filter(|address| contract.has_role(&role, address))
+                .copied()
+                .collect(),
+            name,
+            role,
+            desired: holders,
+        })
+        .collect();
+    plan_role_changes(C::NAME, &roles, caller).map_err(to_error)
 }
 
-fn set_role_supplier(
-    contract: &mut StyksBlockySupplierHostRef,
-    role: &StyksBlockySupplerRole,
-    address: &Address,
+fn apply<C: AccessControlled>(
     env: &HostEnv,
-) -> Result<(), Error> {
-
-    if contract.has_role(&role.role_id(), address) {
-        odra_cli::log(format!("Already has role: {:?} in StyksBlockySupplier", role));
-    } else {
-        odra_cli::log(format!("Granting role: {:?} in StyksBlockySupplier", role));
-        env.set_gas(cspr!(2.5));
-        contract.grant_role(&role.role_id(), address);
+    gas: &mut GasPolicy,
+    contract: &mut C,
+    plan: &[RoleChange],
+    dry_run: bool,
+) {
+    if plan.is_empty() {
+        odra_cli::log(format!("Roles in {} are already set as desired.", C::NAME));
+        return;
     }
-    Ok(())
-}
\ No newline at end of file
+    for change in plan {
+        if dry_run {
+            odra_cli::log(format!("[dry run] {}: {}", C::NAME, change));
+            continue;
+        }
+        odra_cli::log(format!("{}: {}", C::NAME, change));
+        match change {
+            RoleChange::Grant { role, address, .. } => {
+                gas.call(env, C::NAME, "grant_role", || contract.grant_role(role, address))
+            }
+            RoleChange::Revoke { role, address, .. } => {
+                gas.call(env, C::NAME, "revoke_role", || contract.revoke_role(role, address))
+            }
+        }
+    }
+}
+
+fn granted_addresses(env: &HostEnv, contract: &Address) -> Vec<Address> {
+    let event_name = format!("event_{}", <RoleGranted as EventInstance>::name());
+    (0..env.events_count(contract))
+        .filter(|index| {
+            env.get_event_bytes(contract, *index)
+                .ok()
+                .and_then(|bytes| String::from_bytes(&bytes).ok().map(|(name, _)| name))
+                .is_some_and(|name| name == event_name)
+        })
+        .filter_map(|index| env.get_event::<RoleGranted, _>(contract, index as i32).ok())
+        .map(|event| event.address)
+        .collect()
+}
+
+fn to_error<E: ToString>(error: E) -> Error {
+    Error::OdraError { message: error.to_string() }
+}
@@ -0,0 +1,67 @@
+use odra::{host::HostEnv, schema::casper_contract_schema::NamedCLType};
+use odra_cli::{
+    scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
+};
+use styks_contracts::styks_price_feed::StyksPriceFeed;
+
+use crate::feed_status::{FeedStatus, PriceFeedStatus};
+
+pub struct ShowFeed;
+
+impl ScenarioMetadata for ShowFeed {
+    const NAME: &'static str = "ShowFeed";
+    const DESCRIPTION: &'static str =
+        "Shows the config, heartbeat and TWAP state of the StyksPriceFeed contract.";
+}
+
+impl Scenario for ShowFeed {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![CommandArg::new(
+            "json",
+            "Print the status as JSON.",
+            NamedCLType::Bool,
+        )]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        args: Args,
+    ) -> core::result::Result<(), Error> {
+        let json = args.get_single::<bool>("json").unwrap_or(false);
+        let feed = container.contract_ref::<StyksPriceFeed>(env)?;
+        let config = feed.get_config_or_none().ok_or_else(|| Error::OdraError {
+            message: String::from("StyksPriceFeed is not configured."),
+        })?;
+
+        let feeds = config
+            .sorted_price_feed_ids()
+            .into_iter()
+            .map(|id| PriceFeedStatus {
+                twap_store: feed.get_current_twap_store(&id),
+                twap_price: feed.get_twap_price(&id),
+                price_feed_id: id,
+            })
+            .collect();
+        let status = FeedStatus::new(
+            env.block_time_secs(),
+            &config,
+            feed.get_last_heartbeat(),
+            feeds,
+        )
+        .map_err(|error| Error::OdraError {
+            message: format!("Invalid heartbeat config: {:?}", error),
+        })?;
+
+        if json {
+            // Plain stdout, so the output can be piped.
+            println!("{}", status.to_json());
+        } else {
+            for line in status.to_lines() {
+                odra_cli::log(line);
+            }
+        }
+        Ok(())
+    }
+}
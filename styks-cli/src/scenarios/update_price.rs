@@ -1,35 +1,50 @@
-use core::panic;
-use std::path::Path;
+use std::{collections::{BTreeMap, BTreeSet}, panic::AssertUnwindSafe};
 
-use odra::{casper_types::bytesrepr::Bytes, host::HostEnv};
+use odra::{casper_types::bytesrepr::Bytes, host::HostEnv, prelude::OdraError, schema::casper_contract_schema::NamedCLType};
 use odra_cli::{
-    cspr, scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
+    scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
 };
 use styks_blocky_parser::{blocky_claims::BlockyClaims, blocky_output::BlockyOutput};
-use styks_contracts::{styks_blocky_supplier::{StyksBlockySupplier, StyksBlockySupplierHostRef}, styks_price_feed::{StyksPriceFeed, StyksPriceFeedHostRef}};
-use styks_core::heartbeat::Heartbeat;
+use styks_contracts::{styks_blocky_supplier::{QuorumRoundStatus, StyksBlockySupplerError, StyksBlockySupplier, StyksBlockySupplierHostRef}, styks_price_feed::{StyksPriceFeed, StyksPriceFeedConfig, StyksPriceFeedHostRef}};
+use styks_core::{heartbeat::{Heartbeat, HeartbeatWindow}, Price, PriceFeedId};
+
+use crate::clock::current_timestamp_secs;
+use crate::gas::{GasPolicy, DEFAULT_GAS_CONFIG};
+use crate::price_source::PriceSource;
+use crate::producer::{collect_reports, retry_until, AttemptError, ProducerConfig, RetryOutcome, Shutdown, DEFAULT_PRODUCER_CONFIG};
 
 
 pub struct UpdatePrice;
 impl ScenarioMetadata for UpdatePrice {
     const NAME: &'static str = "UpdatePrice";
-    const DESCRIPTION: &'static str = "Updates the price in the PriceFeedManager contract.";
+    const DESCRIPTION: &'static str =
+        "Runs the price producer daemon, which reports the price once per heartbeat until SIGTERM.";
 }
 
 impl Scenario for UpdatePrice {
     fn args(&self) -> Vec<CommandArg> {
-        vec![]
+        vec![CommandArg::new(
+            "config",
+            "Path to the producer config. Defaults to resources/producer.toml.",
+            NamedCLType::String,
+        )]
     }
 
     fn run(
         &self,
         env: &HostEnv,
         container: &DeployedContractsContainer,
-        _args: Args,
+        args: Args,
     ) -> core::result::Result<(), Error> {
-        let mut updater = Updater::new(env.clone(), container)?;
+        let path = args
+            .get_single::<String>("config")
+            .unwrap_or_else(|_| String::from(DEFAULT_PRODUCER_CONFIG));
+        odra_cli::log(format!("Loading producer config from {}.", path));
+        let config = ProducerConfig::load(&path).map_err(|error| Error::OdraError {
+            message: error.to_string(),
+        })?;
+        let mut updater = Updater::new(env.clone(), container, config, Shutdown::on_signals())?;
         updater.start();
-        // updater.report_price();
         Ok(())
     }
 }
@@ -38,52 +53,70 @@ pub struct Updater {
     env: HostEnv,
     feed_contract: StyksPriceFeedHostRef,
     supplier_contract: StyksBlockySupplierHostRef,
-    coingecko_client: CoinGeckoClient,
-    price_feed_id: String,
-    use_blocky_supplier: bool,
+    sources: BTreeMap<PriceFeedId, Box<dyn PriceSource>>,
+    config: ProducerConfig,
+    gas: GasPolicy,
+    shutdown: Shutdown,
 }
 
 impl Updater {
-    pub fn new(env: HostEnv, container: &DeployedContractsContainer) -> Result<Self, Error> {
+    pub fn new(
+        env: HostEnv,
+        container: &DeployedContractsContainer,
+        config: ProducerConfig,
+        shutdown: Shutdown,
+    ) -> Result<Self, Error> {
         let feed_contract = container.contract_ref::<StyksPriceFeed>(&env)?;
         let supplier_contract = container.contract_ref::<StyksBlockySupplier>(&env)?;
-        let coingecko_client = CoinGeckoClient::new();
+        let mut sources = BTreeMap::new();
+        for (price_feed_id, source) in &config.feeds {
+            let source = source.build(&config.blocky).map_err(|message| Error::OdraError {
+                message: format!("Price source of {}: {}", price_feed_id, message),
+            })?;
+            odra_cli::log(format!("[{}] Price source: {}.", price_feed_id, source.name()));
+            sources.insert(price_feed_id.clone(), source);
+        }
+        let gas = GasPolicy::load(DEFAULT_GAS_CONFIG).map_err(|error| Error::OdraError {
+            message: error.to_string(),
+        })?;
         Ok(Updater {
             env,
             feed_contract,
             supplier_contract,
-            coingecko_client,
-            price_feed_id: String::from("CSPRUSD"),
-            use_blocky_supplier: true
+            sources,
+            config,
+            gas,
+            shutdown,
         })
     }
 
     pub fn start(&mut self) {
         odra_cli::log("[x] Starting price update loop.");
 
-        // Fetch the current configuration from the contract.        
-        let config = self.feed_contract.get_config();
-        odra_cli::log(format!("Current config: {:?}", config));
-
-        loop {
-            odra_cli::log("[x] Starting loop.");
-            // Load last heartbeat time.
-            let last_heartbeat = self.feed_contract.get_last_heartbeat().unwrap_or_default();
-            odra_cli::log(format!("Last heartbeat time: {:?}", last_heartbeat));
-
-            // Load current time.
+        while !self.shutdown.is_requested() {
+            // Fetch the current configuration from the contract, so feeds
+            // added in the meantime are picked up.
             let current_time = current_timestamp_secs();
+            let (config, last_heartbeat, heartbeat) = match self.read_feed_state(current_time) {
+                Ok(state) => state,
+                Err(error) => {
+                    let delay = self.config.retry.initial_delay_secs;
+                    odra_cli::log(format!(
+                        "Failed to read the feed state: {}. Retrying in {} seconds.",
+                        error, delay
+                    ));
+                    if !self.shutdown.sleep(delay) {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            odra_cli::log(format!("Current config: {:?}", config));
+            odra_cli::log(format!("Last heartbeat time: {:?}", last_heartbeat));
             odra_cli::log(format!("Current time:        {}", current_time));
-            
-            // Load Heartbeat state.
-            let heartbeat = Heartbeat::new(
-                current_time,
-                config.heartbeat_interval,
-                config.heartbeat_tolerance,
-            ).unwrap();
+
             let heartbeat_status = heartbeat.current_state();
             let missed_heartbeat = heartbeat.count_missed_heartbeats_since(last_heartbeat);
-            // odra_cli::log(format!("Heartbeat status:\n{:#?}", heartbeat_status));
             odra_cli::log(format!(
                 "Missed heartbeats since last heartbeat: {}",
                 missed_heartbeat
@@ -94,7 +127,8 @@ impl Updater {
                 if current_window.middle == last_heartbeat {
                     odra_cli::log("Already updated price in this heartbeat window.");
                 } else {
-                    self.report_price();
+                    let price_feed_ids = config.sorted_price_feed_ids();
+                    self.report_prices_in_window(&current_window, &price_feed_ids);
                 }    
             }
 
@@ -111,150 +145,234 @@ impl Updater {
                 "Sleeping for {} seconds until next heartbeat.",
                 sleep_time
             ));
-            std::thread::sleep(std::time::Duration::from_secs(sleep_time));
-            odra_cli::log("[x] Loop iteration completed.");
-            odra_cli::log("--------------------------------------------------");
+            if !self.shutdown.sleep(sleep_time) {
+                break;
+            }
         }
+        odra_cli::log("[x] Shutdown requested, price update loop stopped.");
     }
 
-    pub fn get_realtime_price(&self) -> u64 {
-        let price_cg = self.coingecko_client.get_price(&self.price_feed_id).unwrap();
-        let price = (price_cg * 100_000.0) as u64;
-        odra_cli::log(format!(
-            "Current price for {}: ${}",
-            self.price_feed_id, price_cg
-        ));
-        price
+    // Reads the feed config, the last heartbeat and the heartbeat at `current_time`.
+    // Node failures panic in the host env, so they are turned into errors.
+    fn read_feed_state(
+        &self,
+        current_time: u64,
+    ) -> Result<(StyksPriceFeedConfig, u64, Heartbeat), String> {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let config = self
+                .feed_contract
+                .try_get_config()
+                .map_err(|e| format!("{:?}", e))?;
+            let last_heartbeat = self
+                .feed_contract
+                .try_get_last_heartbeat()
+                .map_err(|e| format!("{:?}", e))?
+                .unwrap_or_default();
+            let heartbeat = Heartbeat::new(
+                current_time,
+                config.heartbeat_interval,
+                config.heartbeat_tolerance,
+            )
+            .map_err(|e| format!("Invalid heartbeat config: {:?}", e))?;
+            Ok((config, last_heartbeat, heartbeat))
+        }));
+        result.unwrap_or_else(|panic| Err(panic_message(panic)))
     }
 
-    pub fn report_price(&mut self) {
-        if self.use_blocky_supplier {
-            odra_cli::log("Reporting price via Blocky Supplier.");
-            self.report_price_via_blocky_supplier();
-        } else {
-            odra_cli::log("Reporting price directly to the feed.");
-            self.report_price_direct_to_feed();
-        }
+    // Tells whether the window no longer takes reports, because another
+    // producer filled it or its quorum round is committed or missed.
+    fn is_window_done(&self, window: &HeartbeatWindow) -> bool {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let last_heartbeat = self.feed_contract.try_get_last_heartbeat().ok().flatten();
+            let round = self.supplier_contract.try_get_round(window.middle).ok().flatten();
+            last_heartbeat == Some(window.middle)
+                || round.is_some_and(|round| round.status != QuorumRoundStatus::Pending)
+        }));
+        result.unwrap_or(false)
     }
 
-    pub fn report_price_direct_to_feed(
-        &mut self,
-    ) {
-        let current_time = current_timestamp_secs();
-        let price = self.get_realtime_price();
-        odra_cli::log(format!(
-            "Updating price feed {} with price: ${} and timestamp: {}.",
-            self.price_feed_id, price, current_time
-        ));
-        // Send price record to the contract.
-        self.env.set_gas(cspr!(2.5));
-        let result = self.feed_contract.try_add_to_feed(vec![(self.price_feed_id.clone(), price)]);
-        match result {
-            Ok(_) => odra_cli::log("Price updated successfully."),
-            Err(e) => odra_cli::log(format!("Failed to update price: {:?}.", e)),
+    // Reports the prices, retrying the failed feeds until the window closes.
+    // Failures are logged, so the loop moves on to the next heartbeat.
+    fn report_prices_in_window(&mut self, window: &HeartbeatWindow, price_feed_ids: &[PriceFeedId]) {
+        let policy = self.config.retry.clone();
+        let shutdown = self.shutdown;
+        let mut pending: BTreeSet<PriceFeedId> = price_feed_ids.iter().cloned().collect();
+        let outcome = retry_until(
+            &policy,
+            window.end,
+            current_timestamp_secs,
+            |secs| {
+                odra_cli::log(format!("Retrying in {} seconds.", secs));
+                shutdown.sleep(secs)
+            },
+            |attempt| {
+                if attempt > 1 && self.is_window_done(window) {
+                    odra_cli::log("The heartbeat window was filled in the meantime.");
+                    return Ok(());
+                }
+                odra_cli::log(format!("Reporting prices, attempt {}.", attempt));
+                self.report_prices(price_feed_ids, &mut pending)
+            },
+        );
+        match outcome {
+            RetryOutcome::Succeeded { .. } => odra_cli::log("Prices updated successfully."),
+            RetryOutcome::WindowClosed { attempts, last_error } => odra_cli::log(format!(
+                "Heartbeat window closed after {} failed attempts, last error: {}.",
+                attempts, last_error
+            )),
+            RetryOutcome::Stopped { attempts, error } => odra_cli::log(format!(
+                "Stopped reporting after {} attempts: {}.",
+                attempts, error
+            )),
+            RetryOutcome::ShutdownRequested => {
+                odra_cli::log("Shutdown requested, retries stopped.")
+            }
         }
     }
 
-    pub fn report_price_via_blocky_supplier(&mut self) {
-        // Call `make run-no-build` in the blocky-guest directory.
-        odra_cli::log("Calling Blocky service to report price.");
-        self.make_blocky_call();
-        let output = self.read_blocky_output();
-        let ta = output.ta();
-        let signature = ta.signature_bytes();
-        let data = ta.data();
-        let claims = BlockyClaims::decode_fn_call_claims(&data).unwrap();
-        let output_value = claims.output().unwrap();
-        let price = output_value.price;
-        let timestamp = output_value.timestamp;
-        odra_cli::log(format!(
-            "Updating price feed {} with price: ${} and timestamp: {}.",
-            self.price_feed_id, price, timestamp
-        ));
+    // Reports the prices of the pending feeds and removes the reported ones.
+    pub fn report_prices(
+        &mut self,
+        price_feed_ids: &[PriceFeedId],
+        pending: &mut BTreeSet<PriceFeedId>,
+    ) -> Result<(), AttemptError> {
+        let mut permanent = None;
+        // Node and contract failures panic in the host env, so they are
+        // turned into errors that can be retried.
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            // Attested outputs are checked against the supplier's feed mappings.
+            let mappings = match self.config.feeds.values().any(|source| source.is_attested()) {
+                true => self.supplier_contract.get_feed_mappings(),
+                false => vec![],
+            };
+            let submission = collect_reports(price_feed_ids, pending, &self.sources, &mappings);
+            for (id, error) in &submission.failed {
+                odra_cli::log(format!("[{}] Failed to fetch price: {}.", id, error));
+            }
 
-        self.env.set_gas(cspr!(4.0));
-        let result = self.supplier_contract.try_report_signed_prices(
-            Bytes::from(signature),
-            Bytes::from(data),
-        );
-        match result {
-            Ok(_) => odra_cli::log("Price updated successfully."),
-            Err(e) => odra_cli::log(format!("Failed to update price: {:?}.", e)),
-        }
-    }
+            if !submission.direct.is_empty() {
+                match self.report_prices_direct_to_feed(submission.direct.clone()) {
+                    Ok(()) => {
+                        for (id, _) in &submission.direct {
+                            pending.remove(id);
+                        }
+                    }
+                    Err(error) => odra_cli::log(format!("Failed to update prices: {}.", error)),
+                }
+            }
 
-    pub fn make_blocky_call(&self) {
-        let output = std::process::Command::new("make")
-            .arg("run-no-build")
-            .current_dir("blocky-guest")
-            .output()
-            .unwrap();
+            if !submission.attested.is_empty() {
+                match self.report_prices_via_blocky_supplier(&submission.attested) {
+                    Ok(()) => {
+                        for (id, _) in &submission.attested {
+                            pending.remove(id);
+                        }
+                    }
+                    Err(error) => {
+                        odra_cli::log(format!("Failed to update prices: {}.", error));
+                        if let AttemptError::Permanent(error) = error {
+                            permanent = Some(error);
+                        }
+                    }
+                }
+            }
+        }));
+        if let Err(panic) = result {
+            odra_cli::log(format!("Failed to update prices: {}.", panic_message(panic)));
+        }
 
-        if !output.status.success() {
-            let error_message = String::from_utf8_lossy(&output.stderr);
-            panic!("Failed to call Blocky service: {}", error_message);
+        if let Some(error) = permanent {
+            return Err(AttemptError::Permanent(error));
+        }
+        match pending.is_empty() {
+            true => Ok(()),
+            false => {
+                let ids: Vec<&str> = pending.iter().map(String::as_str).collect();
+                Err(AttemptError::Retryable(format!("No price reported for {}", ids.join(", "))))
+            }
         }
     }
 
-    pub fn read_blocky_output(&self) -> BlockyOutput {
-        let manifest_dir = env!("CARGO_MANIFEST_DIR");
-        let path = Path::new(manifest_dir).join("../blocky-guest/tmp/out.json");
-        BlockyOutput::try_from_file(path).unwrap()
+    pub fn report_prices_direct_to_feed(
+        &mut self,
+        prices: Vec<(PriceFeedId, Price)>,
+    ) -> Result<(), String> {
+        let current_time = current_timestamp_secs();
+        for (id, price) in &prices {
+            odra_cli::log(format!(
+                "[{}] Updating price feed with price: ${} and timestamp: {}.",
+                id, price, current_time
+            ));
+        }
+        // Send price records to the contract.
+        self.gas
+            .call(&self.env, "StyksPriceFeed", "add_to_feed", || {
+                self.feed_contract.try_add_to_feed(prices)
+            })
+            .map_err(|e| format!("{:?}", e))
     }
-        
-}
-
-// --- Coingecko clinet ---
 
-pub struct CoinGeckoClient {
-    api_key: String,
-}
-
-impl CoinGeckoClient {
-    pub fn new() -> Self {
-        // Read COINGECKO_PRO_API_KEY key from environment variable.
-        let api_key = std::env::var("COINGECKO_PRO_API_KEY")
-            .expect("COINGECKO_PRO_API_KEY environment variable not set");
-        CoinGeckoClient { api_key }
-    }
+    // A single output goes to `report_signed_prices`, more of them are
+    // posted together with `report_signed_prices_batch`.
+    pub fn report_prices_via_blocky_supplier(
+        &mut self,
+        outputs: &[(PriceFeedId, BlockyOutput)],
+    ) -> Result<(), AttemptError> {
+        let mut reports = Vec::new();
+        for (price_feed_id, output) in outputs {
+            let ta = output.ta().map_err(|e| {
+                AttemptError::Retryable(format!("[{}] Invalid Blocky attestation: {}", price_feed_id, e))
+            })?;
+            let signature = ta.recoverable_signature_bytes();
+            let data = ta.data();
+            let output_value = BlockyClaims::decode_fn_call_claims(data)
+                .and_then(|claims| claims.output())
+                .map_err(|e| {
+                    AttemptError::Retryable(format!("[{}] Invalid Blocky claims: {:?}", price_feed_id, e))
+                })?;
+            odra_cli::log(format!(
+                "[{}] Updating price feed with price: ${} and timestamp: {}.",
+                price_feed_id, output_value.price, output_value.timestamp
+            ));
+            reports.push((Bytes::from(signature), Bytes::from(data.to_vec())));
+        }
 
-    pub fn get_price(&self, price_feed_id: &str) -> Result<f64, String> {
-        let currency = match price_feed_id {
-            "CSPRUSD" => "casper-network",
-            "BTCUSD" => "bitcoin",
-            _ => return Err(format!("Unsupported price feed ID: {}", price_feed_id)),
-        };
-        let url = format!(
-            "https://api.coingecko.com/api/v3/simple/price?vs_currencies=usd&ids={}",
-            currency
-        );
-        let response = ureq::get(url)
-            .header("x-cg-demo-api-key", &self.api_key)
-            .call();
-        match response {
-            Ok(mut resp) => {
-                let body = resp.body_mut().read_to_string().unwrap();
-                let json: serde_json::Value = serde_json::from_str(&body)
-                    .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-                if let Some(price) = json[currency]["usd"].as_f64() {
-                    return Ok(price);
-                } else {
-                    // odra_cli::log("Price not found in response.");
-                    // odra_cli::log(format!("Response: {}", json));
-                    return Err("Price not found in response".to_string());
-                }
-            }
-            Err(e) => Err(format!("Failed to fetch price: {}", e)),
+        if let [(signature, data)] = reports.as_slice() {
+            let (signature, data) = (signature.clone(), data.clone());
+            return self
+                .gas
+                .call(&self.env, "StyksBlockySupplier", "report_signed_prices", || {
+                    self.supplier_contract.try_report_signed_prices(signature, data)
+                })
+                .map_err(supplier_error);
         }
+        self.gas
+            .call(&self.env, "StyksBlockySupplier", "report_signed_prices_batch", || {
+                self.supplier_contract.try_report_signed_prices_batch(reports)
+            })
+            .map_err(supplier_error)
     }
 }
 
-fn current_timestamp_secs() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
+// A finalized round or an accepted report can't change within the window,
+// so those reverts stop the retries.
+fn supplier_error(error: OdraError) -> AttemptError {
+    let permanent: [OdraError; 2] = [
+        StyksBlockySupplerError::RoundAlreadyFinalized.into(),
+        StyksBlockySupplerError::ProducerAlreadyReported.into(),
+    ];
+    match permanent.contains(&error) {
+        true => AttemptError::Permanent(format!("{:?}", error)),
+        false => AttemptError::Retryable(format!("{:?}", error)),
+    }
+}
 
-    let start = SystemTime::now();
-    let timestamp = start.duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs();
-    timestamp
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::from("unknown panic"),
+        },
+    }
 }
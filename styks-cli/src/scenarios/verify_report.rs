@@ -0,0 +1,103 @@
+use odra::{contract_def::HasIdent, host::HostEnv, schema::casper_contract_schema::NamedCLType};
+use odra_cli::{
+    scenario::{Args, Error, Scenario, ScenarioMetadata}, CommandArg, ContractProvider, DeployedContractsContainer
+};
+use styks_blocky_parser::blocky_output::BlockyOutput;
+use styks_contracts::{styks_blocky_supplier::StyksBlockySupplier, styks_price_feed::StyksPriceFeed};
+
+use crate::{
+    clock::current_timestamp_secs,
+    deployment::DeploymentConfig,
+    verifier::{unchecked_conditions, verify_output, VerifierContext},
+};
+
+pub struct VerifyReport;
+
+impl ScenarioMetadata for VerifyReport {
+    const NAME: &'static str = "VerifyReport";
+    const DESCRIPTION: &'static str =
+        "Checks a Blocky output the way StyksBlockySupplier would, without submitting it.";
+}
+
+impl Scenario for VerifyReport {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![
+            CommandArg::new("output", "Path to the Blocky output JSON.", NamedCLType::String)
+                .required(),
+            CommandArg::new(
+                "config",
+                "Path to a deployment config to check against. Defaults to the on-chain config.",
+                NamedCLType::String,
+            ),
+            CommandArg::new(
+                "time",
+                "Time in seconds the timestamp is checked against. Defaults to now.",
+                NamedCLType::U64,
+            ),
+        ]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        args: Args,
+    ) -> core::result::Result<(), Error> {
+        let path = args.get_single::<String>("output")?;
+        let output = BlockyOutput::try_from_file(&path).map_err(|error| Error::OdraError {
+            message: format!("Failed to read Blocky output {}: {}", path, error),
+        })?;
+
+        let (config, mappings, price_feed_ids, current_time) = match args.get_single::<String>("config") {
+            Ok(config_path) => {
+                odra_cli::log(format!("Checking against deployment config {}.", config_path));
+                let deployment = DeploymentConfig::load(&config_path).map_err(to_error)?;
+                // The feed address isn't checked, any address will do.
+                let feed_address = container
+                    .address_by_name(&StyksPriceFeed::ident())
+                    .unwrap_or_else(|| env.caller());
+                let config = deployment.supplier_config(feed_address).map_err(to_error)?;
+                let price_feed_ids = deployment.feed_config().price_feed_ids;
+                (config, deployment.mappings(), price_feed_ids, current_timestamp_secs())
+            }
+            Err(_) => {
+                odra_cli::log("Checking against the on-chain config.");
+                let supplier = container.contract_ref::<StyksBlockySupplier>(env)?;
+                let config = supplier.get_config_or_none().ok_or_else(|| Error::OdraError {
+                    message: String::from("StyksBlockySupplier is not configured."),
+                })?;
+                let feed = container.contract_ref::<StyksPriceFeed>(env)?;
+                let price_feed_ids = feed.get_config().price_feed_ids;
+                (config, supplier.get_feed_mappings(), price_feed_ids, env.block_time_secs())
+            }
+        };
+        let current_time = args.get_single::<u64>("time").unwrap_or(current_time);
+
+        let context = VerifierContext {
+            config: &config,
+            mappings: &mappings,
+            price_feed_ids: &price_feed_ids,
+            current_time,
+        };
+        let checks = verify_output(&output, &context);
+        for check in &checks {
+            odra_cli::log(check.to_string());
+        }
+        let failed = checks.iter().filter(|check| !check.passed()).count();
+        if failed > 0 {
+            return Err(Error::OdraError {
+                message: format!("{} of {} checks didn't pass.", failed, checks.len()),
+            });
+        }
+        // The remaining conditions depend on when and by whom the report is sent.
+        odra_cli::log("All checks passed. Not checked:");
+        for condition in unchecked_conditions(&config, &price_feed_ids) {
+            odra_cli::log(format!("- {}", condition));
+        }
+        Ok(())
+    }
+}
+
+fn to_error<E: ToString>(error: E) -> Error {
+    Error::OdraError { message: error.to_string() }
+}
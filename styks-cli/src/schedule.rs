@@ -0,0 +1,197 @@
+//! Heartbeat and TWAP schedule of a candidate StyksPriceFeed config.
+
+use styks_contracts::styks_price_feed::{StyksPriceFeedConfig, StyksPriceFeedError};
+use styks_core::{
+    heartbeat::{Heartbeat, HeartbeatWindow},
+    twap::TWAP,
+};
+
+use crate::feed_status::parse_duration;
+
+#[derive(Debug, PartialEq)]
+pub struct SchedulePlan {
+    pub current_time: u64,
+    // The open window, if any, followed by the upcoming ones.
+    pub windows: Vec<HeartbeatWindow>,
+    // Time between the oldest and the newest value of a full TWAP window.
+    pub twap_span: u64,
+    // Updates needed after deployment before the TWAP has a price.
+    pub updates_until_first_price: u32,
+    // Consecutive missed heartbeats after which the TWAP returns `None`.
+    pub misses_until_none: u32,
+    // Longest time since the last update the TWAP still returns a price.
+    pub max_staleness: u64,
+}
+
+// Validates the config and plans the next `count` heartbeat windows.
+pub fn plan_schedule(
+    config: &StyksPriceFeedConfig,
+    current_time: u64,
+    count: usize,
+) -> Result<SchedulePlan, String> {
+    config.validate().map_err(explain)?;
+    let interval = config.heartbeat_interval;
+    let tolerance = config.heartbeat_tolerance;
+    let heartbeat = Heartbeat::new(current_time, interval, tolerance)
+        .map_err(|error| explain(error.into()))?;
+
+    let state = heartbeat.current_state();
+    let mut windows: Vec<HeartbeatWindow> = state.current.into_iter().collect();
+    let mut middle = state.next.middle;
+    while windows.len() < count {
+        windows.push(HeartbeatWindow {
+            start: middle.saturating_sub(tolerance),
+            middle,
+            end: middle + tolerance,
+        });
+        middle += interval;
+    }
+
+    let misses_until_none = misses_until_none(config).map_err(|error| explain(error.into()))?;
+    Ok(SchedulePlan {
+        current_time,
+        windows,
+        twap_span: (config.twap_window as u64 - 1) * interval,
+        updates_until_first_price: config.twap_window - config.twap_tolerance,
+        misses_until_none,
+        // The feed counts a heartbeat as missed once its window has ended.
+        max_staleness: misses_until_none as u64 * interval + tolerance,
+    })
+}
+
+// Adds missed values to a full TWAP window until it has no price.
+fn misses_until_none(config: &StyksPriceFeedConfig) -> Result<u32, styks_core::twap::TWAPError> {
+    let values = vec![Some(1); config.twap_window as usize];
+    let mut twap = TWAP::new(config.twap_window, config.twap_tolerance, values)?;
+    let mut misses = 0;
+    while twap.calculate().is_some() {
+        twap.add_missed_value();
+        misses += 1;
+    }
+    Ok(misses)
+}
+
+impl SchedulePlan {
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("Upcoming heartbeat windows from {}:", self.current_time)];
+        for window in &self.windows {
+            let status = match window.is_in_window(self.current_time) {
+                true => String::from("open now"),
+                false => format!("opens in {}", parse_duration(window.start - self.current_time)),
+            };
+            lines.push(format!(
+                "  {} - {} - {} ({})",
+                window.start, window.middle, window.end, status
+            ));
+        }
+        lines.push(format!(
+            "The TWAP averages prices from a span of {}.",
+            parse_duration(self.twap_span)
+        ));
+        lines.push(format!(
+            "After deployment the first price is available after {} updates.",
+            self.updates_until_first_price
+        ));
+        lines.push(format!(
+            "The feed returns no price after {} consecutive missed heartbeats, {} after the last update.",
+            self.misses_until_none,
+            parse_duration(self.max_staleness)
+        ));
+        lines
+    }
+}
+
+// Plain explanation of a config error.
+pub fn explain(error: StyksPriceFeedError) -> String {
+    use StyksPriceFeedError::*;
+    match error {
+        HeartbeatIntervalShouldBeGreaterThanZero => {
+            String::from("heartbeat_interval is 0, heartbeats need a positive interval.")
+        }
+        HeartbeatTolaranceShouldBeLessThanHalfOfInterval => String::from(
+            "heartbeat_tolerance must be less than half of heartbeat_interval, otherwise the windows of two heartbeats overlap.",
+        ),
+        TWAPWindowCannotBeZero => {
+            String::from("twap_window is 0, the TWAP needs at least one price.")
+        }
+        TWAPToleranceMustBeLessThanWindow => String::from(
+            "twap_tolerance must be less than twap_window, otherwise the TWAP could be computed from no prices.",
+        ),
+        TWAPTooManyValues => String::from("The TWAP store holds more values than twap_window."),
+        PriceFeedIdIsEmptyString => String::from("One of price_feed_ids is an empty string."),
+        PriceFeedIdNotUnique => String::from("price_feed_ids contains the same id more than once."),
+        _ => String::from("The config is invalid."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StyksPriceFeedConfig {
+        StyksPriceFeedConfig {
+            heartbeat_interval: 1800,
+            heartbeat_tolerance: 60,
+            twap_window: 3,
+            twap_tolerance: 1,
+            price_feed_ids: vec![String::from("CSPRUSD")],
+        }
+    }
+
+    #[test]
+    fn test_plan_schedule() {
+        let plan = plan_schedule(&config(), 3570, 3).unwrap();
+        let middles: Vec<u64> = plan.windows.iter().map(|window| window.middle).collect();
+        assert_eq!(middles, vec![3600, 5400, 7200]);
+        assert_eq!(plan.twap_span, 3600);
+        assert_eq!(plan.updates_until_first_price, 2);
+        assert_eq!(plan.misses_until_none, 2);
+        assert_eq!(plan.max_staleness, 3660);
+
+        let lines = plan.to_lines();
+        assert_eq!(lines[1], "  3540 - 3600 - 3660 (open now)");
+        assert_eq!(lines[2], "  5340 - 5400 - 5460 (opens in 29 minutes, 30 seconds)");
+        assert_eq!(
+            lines[6],
+            "The feed returns no price after 2 consecutive missed heartbeats, 1 hour, 1 minute after the last update."
+        );
+
+        // Outside of a window only the upcoming ones are listed.
+        let plan = plan_schedule(&config(), 100, 2).unwrap();
+        let middles: Vec<u64> = plan.windows.iter().map(|window| window.middle).collect();
+        assert_eq!(middles, vec![1800, 3600]);
+    }
+
+    #[test]
+    fn test_max_staleness_matches_heartbeat() {
+        // The heartbeat counts the misses the same way the plan does.
+        let config = config();
+        let plan = plan_schedule(&config, 0, 1).unwrap();
+        let last_update = 1800;
+        let count = |time| {
+            Heartbeat::new(time, config.heartbeat_interval, config.heartbeat_tolerance)
+                .unwrap()
+                .count_missed_heartbeats_since(last_update)
+        };
+        assert_eq!(count(last_update + plan.max_staleness), 1);
+        assert_eq!(count(last_update + plan.max_staleness + 1), 2);
+    }
+
+    #[test]
+    fn test_explain_errors() {
+        let mut invalid = config();
+        invalid.heartbeat_tolerance = 900;
+        let error = plan_schedule(&invalid, 0, 1).unwrap_err();
+        assert!(error.starts_with("heartbeat_tolerance must be less than half"));
+
+        let mut invalid = config();
+        invalid.twap_tolerance = 3;
+        let error = plan_schedule(&invalid, 0, 1).unwrap_err();
+        assert!(error.starts_with("twap_tolerance must be less than twap_window"));
+
+        let mut invalid = config();
+        invalid.price_feed_ids.push(String::from("CSPRUSD"));
+        let error = plan_schedule(&invalid, 0, 1).unwrap_err();
+        assert_eq!(error, "price_feed_ids contains the same id more than once.");
+    }
+}
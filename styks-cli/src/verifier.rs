@@ -0,0 +1,371 @@
+//! Offline checks of a Blocky output, in the order `report_signed_prices`
+//! of `StyksBlockySupplier` runs them.
+
+use odra::prelude::OdraError;
+use styks_blocky_parser::{blocky_claims::BlockyClaims, blocky_output::BlockyOutput};
+use styks_contracts::{
+    styks_blocky_supplier::{StyksBlockySupplerConfig, StyksBlockySupplerError},
+    styks_price_feed::StyksPriceFeedError,
+};
+use styks_core::{PriceFeedId, SourceKey};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckResult {
+    Pass(String),
+    Fail(String),
+    // Not run, because a check it depends on failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub result: CheckResult,
+}
+
+impl Check {
+    fn new(name: &'static str, result: Result<String, String>) -> Self {
+        let result = match result {
+            Ok(detail) => CheckResult::Pass(detail),
+            Err(reason) => CheckResult::Fail(reason),
+        };
+        Check { name, result }
+    }
+
+    pub fn passed(&self) -> bool {
+        matches!(self.result, CheckResult::Pass(_))
+    }
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.result {
+            CheckResult::Pass(detail) => write!(f, "[PASS] {}: {}", self.name, detail),
+            CheckResult::Fail(reason) => write!(f, "[FAIL] {}: {}", self.name, reason),
+            CheckResult::Skipped => write!(f, "[SKIP] {}", self.name),
+        }
+    }
+}
+
+// Supplier state the output is checked against.
+pub struct VerifierContext<'a> {
+    pub config: &'a StyksBlockySupplerConfig,
+    pub mappings: &'a [(SourceKey, PriceFeedId)],
+    // PriceFeedIds of the StyksPriceFeed config.
+    pub price_feed_ids: &'a [PriceFeedId],
+    pub current_time: u64,
+}
+
+pub fn verify_output(output: &BlockyOutput, context: &VerifierContext) -> Vec<Check> {
+    let config = context.config;
+    let mut checks = Vec::new();
+    let ta = match output.ta() {
+        Ok(ta) => ta,
+        Err(error) => {
+            checks.push(Check::new("attestation", Err(error.to_string())));
+            return skip_rest(checks);
+        }
+    };
+    checks.push(Check::new("attestation", Ok(String::from("decoded"))));
+    let signature = ta.recoverable_signature_bytes();
+    let data = ta.data();
+
+    let signer = match &config.signer_address {
+        Some(signer) => format!("0x{}", hex::encode(signer)),
+        None => hex::encode(config.public_key()),
+    };
+    let result = config
+        .check_signature(&signature, data)
+        .map(|_| format!("signed by {}", signer))
+        .map_err(|error| fail(format!("not signed by {}", signer), error));
+    checks.push(Check::new("signature", result));
+
+    let claims = match BlockyClaims::decode_fn_call_claims(data) {
+        Ok(claims) => claims,
+        Err(error) => {
+            let reason = fail(format!("{:?}", error), StyksBlockySupplerError::from(error));
+            checks.push(Check::new("claims", Err(reason)));
+            return skip_rest(checks);
+        }
+    };
+    checks.push(Check::new("claims", Ok(format!("function {}", claims.function()))));
+
+    let hash = claims.hash_of_code();
+    let result = match config.check_wasm_hash(&hash) {
+        Ok(()) => Ok(hash),
+        Err(error) => Err(fail(format!("got {} instead of {}", hash, config.wasm_hash), error)),
+    };
+    checks.push(Check::new("guest hash", result));
+
+    let output = match claims.output() {
+        Ok(output) => output,
+        Err(error) => {
+            let reason = fail(format!("{:?}", error), StyksBlockySupplerError::from(error));
+            checks.push(Check::new("output", Err(reason)));
+            return skip_rest(checks);
+        }
+    };
+    checks.push(Check::new(
+        "output",
+        Ok(format!(
+            "{} {}/{} price {} at {}",
+            output.market, output.coin_id, output.currency, output.price, output.timestamp
+        )),
+    ));
+
+    let current_time = context.current_time;
+    let detail = format!(
+        "{}s from {}, tolerance {}s",
+        output.timestamp.abs_diff(current_time),
+        current_time,
+        config.timestamp_tolerance
+    );
+    let result = match config.check_timestamp(output.timestamp, current_time) {
+        Ok(()) => Ok(detail),
+        Err(error) => Err(fail(detail, error)),
+    };
+    checks.push(Check::new("timestamp", result));
+
+    let source_key = SourceKey::new(&output.market, &output.coin_id, &output.currency);
+    let price_feed_id = context
+        .mappings
+        .iter()
+        .find(|(key, _)| *key == source_key)
+        .map(|(_, id)| id.clone());
+    let price_feed_id = match price_feed_id {
+        Some(id) => {
+            checks.push(Check::new("feed mapping", Ok(id.clone())));
+            id
+        }
+        None => {
+            let reason = fail(
+                format!("no feed is mapped to {:?}", source_key),
+                StyksBlockySupplerError::PriceFeedIdNotFound,
+            );
+            checks.push(Check::new("feed mapping", Err(reason)));
+            return skip_rest(checks);
+        }
+    };
+
+    let result = match context.price_feed_ids.contains(&price_feed_id) {
+        true => Ok(String::from("in the feed config")),
+        false => Err(fail(
+            format!("{} is not in the feed config", price_feed_id),
+            StyksPriceFeedError::PriceFeedIdsMissmatch,
+        )),
+    };
+    checks.push(Check::new("feed id", result));
+
+    let expectation = config.expectation(&price_feed_id);
+    let result = match config.check_output(&price_feed_id, &output) {
+        Ok(()) if expectation.is_some() => Ok(String::from("matches the expectation")),
+        Ok(()) => Ok(String::from("no expectation, price is not zero")),
+        Err(error) => {
+            let reason = match expectation {
+                Some(expectation) if output.price != 0 => format!(
+                    "expected {} {}/{} priced {} - {}",
+                    expectation.market,
+                    expectation.coin_id,
+                    expectation.currency,
+                    expectation.min_price,
+                    expectation.max_price
+                ),
+                _ => String::from("price is zero"),
+            };
+            Err(fail(reason, error))
+        }
+    };
+    checks.push(Check::new("expected output", result));
+    checks
+}
+
+const CHECKS: [&str; 9] = [
+    "attestation",
+    "signature",
+    "claims",
+    "guest hash",
+    "output",
+    "timestamp",
+    "feed mapping",
+    "feed id",
+    "expected output",
+];
+
+// Conditions of an accepted report that depend on chain state or on the
+// sender, so `verify_output` doesn't check them.
+pub fn unchecked_conditions(
+    config: &StyksBlockySupplerConfig,
+    price_feed_ids: &[PriceFeedId],
+) -> Vec<&'static str> {
+    let mut conditions = vec!["the report is sent in an open heartbeat window"];
+    match config.quorum {
+        Some(_) => conditions.extend([
+            "the sender has the PriceProducer role",
+            "the quorum round of the window is pending",
+            "the sender hasn't reported in this round",
+        ]),
+        None => {
+            conditions.push("the feed wasn't updated in this window");
+            if price_feed_ids.len() > 1 {
+                conditions.push("the batch has a report of every feed id");
+            }
+        }
+    }
+    conditions
+}
+
+// Marks the checks that didn't run as skipped.
+fn skip_rest(mut checks: Vec<Check>) -> Vec<Check> {
+    let last = checks.last().map(|check| check.name).unwrap_or_default();
+    let position = CHECKS.iter().position(|name| *name == last).unwrap_or_default();
+    for name in CHECKS.iter().skip(position + 1) {
+        if !checks.iter().any(|check| check.name == *name) {
+            checks.push(Check { name, result: CheckResult::Skipped });
+        }
+    }
+    checks
+}
+
+// Adds the code of the error the contract would revert with.
+fn fail<E: Into<OdraError>>(reason: String, error: E) -> String {
+    format!("{} (error code {})", reason, error.into().code())
+}
+
+#[cfg(test)]
+mod tests {
+    use odra::{casper_types::bytesrepr::Bytes, prelude::Address};
+    use styks_contracts::styks_blocky_supplier::{
+        StyksBlockySupplerExpectation, StyksBlockySupplerQuorumConfig,
+    };
+
+    use super::*;
+
+    const WASM_HASH: &str = "baadaf829374304416a3c78a7c1118eb6784d3585c8cb5b18fa95c38cb8e4382fda8e149c4d05769d513af599445237dcc87d232da8f51251f0ad6dd1aff5b17";
+
+    fn setup() -> (BlockyOutput, StyksBlockySupplerConfig, Vec<(SourceKey, PriceFeedId)>, u64) {
+        let output = styks_blocky_parser::block_output_for_tests();
+        let config = StyksBlockySupplerConfig {
+            wasm_hash: String::from(WASM_HASH),
+            public_key: Bytes::from(output.public_key_bytes().unwrap()),
+            price_feed_address: Address::new(
+                "hash-fe8b1829844276b21e8d577d525808ed36cc9e12c06b5acfb897204e5b49c5a9",
+            )
+            .unwrap(),
+            timestamp_tolerance: 60,
+            quorum: None,
+            signer_address: None,
+            expectations: vec![],
+        };
+        let mappings = vec![(SourceKey::new("Gate", "CSPR", "USD"), String::from("CSPRUSD"))];
+        let timestamp = output.claims().unwrap().output().unwrap().timestamp;
+        (output, config, mappings, timestamp)
+    }
+
+    fn results(checks: &[Check]) -> Vec<String> {
+        checks
+            .iter()
+            .map(|check| match &check.result {
+                CheckResult::Pass(_) => format!("{}: pass", check.name),
+                CheckResult::Fail(_) => format!("{}: fail", check.name),
+                CheckResult::Skipped => format!("{}: skip", check.name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_output() {
+        let (output, config, mappings, timestamp) = setup();
+        let context = VerifierContext {
+            config: &config,
+            mappings: &mappings,
+            price_feed_ids: &[String::from("CSPRUSD")],
+            current_time: timestamp + 10,
+        };
+        let checks = verify_output(&output, &context);
+        assert_eq!(checks.len(), CHECKS.len());
+        assert!(checks.iter().all(Check::passed), "{:?}", checks);
+        assert_eq!(checks[6].to_string(), "[PASS] feed mapping: CSPRUSD");
+    }
+
+    #[test]
+    fn test_verify_output_failures() {
+        let (output, mut config, mappings, timestamp) = setup();
+        config.wasm_hash = String::from("00");
+        config.public_key = Bytes::from(vec![2u8; 33]);
+        config.expectations = vec![StyksBlockySupplerExpectation {
+            price_feed_id: String::from("CSPRUSD"),
+            market: String::from("Gate"),
+            coin_id: String::from("CSPR"),
+            currency: String::from("USD"),
+            min_price: 1,
+            max_price: 2,
+        }];
+        let context = VerifierContext {
+            config: &config,
+            mappings: &mappings,
+            price_feed_ids: &[String::from("CSPRUSD")],
+            current_time: timestamp + 61,
+        };
+        let checks = verify_output(&output, &context);
+        assert_eq!(
+            results(&checks),
+            vec![
+                "attestation: pass",
+                "signature: fail",
+                "claims: pass",
+                "guest hash: fail",
+                "output: pass",
+                "timestamp: fail",
+                "feed mapping: pass",
+                "feed id: pass",
+                "expected output: fail",
+            ]
+        );
+        assert_eq!(
+            checks[8].to_string(),
+            "[FAIL] expected output: expected Gate CSPR/USD priced 1 - 2 (error code 46505)"
+        );
+
+        // Without a mapping the expectation can't be checked.
+        let context = VerifierContext {
+            config: &config,
+            mappings: &[],
+            price_feed_ids: &[String::from("CSPRUSD")],
+            current_time: timestamp,
+        };
+        let checks = verify_output(&output, &context);
+        assert_eq!(
+            results(&checks)[6..],
+            ["feed mapping: fail", "feed id: skip", "expected output: skip"]
+        );
+
+        // The mapped id has to be one of the feed's ids.
+        let context = VerifierContext {
+            config: &config,
+            mappings: &mappings,
+            price_feed_ids: &[String::from("BTCUSD")],
+            current_time: timestamp,
+        };
+        let checks = verify_output(&output, &context);
+        assert_eq!(
+            checks[7].to_string(),
+            "[FAIL] feed id: CSPRUSD is not in the feed config (error code 45022)"
+        );
+    }
+
+    #[test]
+    fn test_unchecked_conditions() {
+        let (_, mut config, _, _) = setup();
+        let ids = [String::from("CSPRUSD"), String::from("BTCUSD")];
+        assert_eq!(unchecked_conditions(&config, &ids[..1]).len(), 2);
+        assert_eq!(
+            unchecked_conditions(&config, &ids).last(),
+            Some(&"the batch has a report of every feed id")
+        );
+
+        config.quorum = Some(StyksBlockySupplerQuorumConfig { threshold: 2, tolerance_bps: 100 });
+        let conditions = unchecked_conditions(&config, &ids);
+        assert!(conditions.contains(&"the sender has the PriceProducer role"));
+        assert!(conditions.contains(&"the quorum round of the window is pending"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Checks of Blocky attestations, shared by the contracts that take them.
+//! They return errors instead of reverting, so the CLI can run them offline.
+//! Each contract maps `AttestationError` into its own error codes.
+
+use styks_blocky_parser::verify::{self, VerificationError};
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum AttestationError {
+    WasmHashIsEmpty,
+    WasmHashNotHex,
+    TimestampToleranceIsZero,
+    BadWasmHash,
+    TimestampOutOfRange,
+    Verification(VerificationError),
+}
+
+impl From<VerificationError> for AttestationError {
+    fn from(error: VerificationError) -> Self {
+        AttestationError::Verification(error)
+    }
+}
+
+// --- Config checks ---
+
+// The wasm hash is the hex encoded SHA3-512 of the guest program.
+pub fn validate_wasm_hash(wasm_hash: &str) -> Result<(), AttestationError> {
+    if wasm_hash.is_empty() {
+        return Err(AttestationError::WasmHashIsEmpty);
+    }
+    if wasm_hash.len() & 1 != 0 || !wasm_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AttestationError::WasmHashNotHex);
+    }
+    Ok(())
+}
+
+pub fn validate_public_key(public_key: &[u8]) -> Result<(), AttestationError> {
+    verify::eth_address(public_key)?;
+    Ok(())
+}
+
+pub fn validate_timestamp_tolerance(tolerance: u64) -> Result<(), AttestationError> {
+    match tolerance {
+        0 => Err(AttestationError::TimestampToleranceIsZero),
+        _ => Ok(()),
+    }
+}
+
+// --- Report checks ---
+
+// With a signer address the signer is recovered from the 65 bytes signature,
+// otherwise the signature is checked against the public key.
+pub fn check_signature(
+    public_key: &[u8],
+    signer_address: Option<&[u8]>,
+    signature: &[u8],
+    data: &[u8],
+) -> Result<(), AttestationError> {
+    match signer_address {
+        Some(signer) => verify::verify_signer(signer, signature, data)?,
+        None => verify::verify_signature(public_key, signature, data)?,
+    };
+    Ok(())
+}
+
+pub fn check_wasm_hash(wasm_hash: &str, hash_of_code: &str) -> Result<(), AttestationError> {
+    match hash_of_code == wasm_hash {
+        true => Ok(()),
+        false => Err(AttestationError::BadWasmHash),
+    }
+}
+
+pub fn check_timestamp(
+    reported: u64,
+    current_time: u64,
+    tolerance: u64,
+) -> Result<(), AttestationError> {
+    if reported < current_time.saturating_sub(tolerance) || reported > current_time + tolerance {
+        return Err(AttestationError::TimestampOutOfRange);
+    }
+    Ok(())
+}
+
+// Breaks a valid config in a way `set_config` has to reject.
+#[cfg(test)]
+pub(crate) type Breaker<C> = fn(&mut C);
+
+// Checks that each broken copy of the config is rejected with its error.
+#[cfg(test)]
+pub(crate) fn assert_rejected_configs<C: Clone, E: Into<odra::prelude::OdraError>>(
+    config: &C,
+    cases: Vec<(Breaker<C>, E)>,
+    mut set_config: impl FnMut(C) -> odra::prelude::OdraResult<()>,
+) {
+    for (breaker, error) in cases {
+        let mut invalid = config.clone();
+        breaker(&mut invalid);
+        assert_eq!(set_config(invalid), Err(error.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate_wasm_hash("da7afeed"), Ok(()));
+        assert_eq!(validate_wasm_hash(""), Err(AttestationError::WasmHashIsEmpty));
+        assert_eq!(validate_wasm_hash("xyz0"), Err(AttestationError::WasmHashNotHex));
+        assert_eq!(validate_wasm_hash("abc"), Err(AttestationError::WasmHashNotHex));
+
+        let invalid = validate_public_key(&[4u8; 65]);
+        assert_eq!(invalid, Err(VerificationError::InvalidPublicKey.into()));
+
+        assert_eq!(validate_timestamp_tolerance(1), Ok(()));
+        assert_eq!(validate_timestamp_tolerance(0), Err(AttestationError::TimestampToleranceIsZero));
+    }
+
+    #[test]
+    fn test_check_timestamp() {
+        assert_eq!(check_timestamp(1000, 1000, 10), Ok(()));
+        assert_eq!(check_timestamp(990, 1000, 10), Ok(()));
+        assert_eq!(check_timestamp(1010, 1000, 10), Ok(()));
+        assert_eq!(check_timestamp(989, 1000, 10), Err(AttestationError::TimestampOutOfRange));
+        assert_eq!(check_timestamp(1011, 1000, 10), Err(AttestationError::TimestampOutOfRange));
+
+        // Near zero the lower bound saturates.
+        assert_eq!(check_timestamp(0, 5, 10), Ok(()));
+    }
+
+    #[test]
+    fn test_check_wasm_hash() {
+        assert_eq!(check_wasm_hash("da7afeed", "da7afeed"), Ok(()));
+        assert_eq!(check_wasm_hash("da7afeed", "0123"), Err(AttestationError::BadWasmHash));
+    }
+}
@@ -3,5 +3,11 @@
 
 extern crate alloc;
 
+pub mod attestation;
 pub mod styks_price_feed;
-pub mod styks_blocky_supplier;
\ No newline at end of file
+pub mod styks_blocky_supplier;
+pub mod styks_admin_action;
+pub mod styks_governance;
+pub mod styks_multisig;
+pub mod styks_data_feed;
+pub mod styks_balance_feed;
@@ -0,0 +1,104 @@
+use odra::{prelude::*, ContractEnv, ContractRef};
+use odra_modules::access::Role;
+use styks_core::{PriceFeedId, SourceKey};
+
+use crate::{
+    styks_blocky_supplier::{StyksBlockySupplerConfig, StyksBlockySupplierContractRef},
+    styks_price_feed::{StyksPriceFeedConfig, StyksPriceFeedContractRef},
+};
+
+// --- Admin Actions ---
+
+// Administrative calls that an admin contract (like the governance)
+// can perform on behalf of its members.
+
+#[odra::odra_type]
+pub enum StyksContract {
+    PriceFeed,
+    BlockySupplier,
+}
+
+#[odra::odra_type]
+pub enum StyksAdminAction {
+    SetPriceFeedConfig(StyksPriceFeedConfig),
+    SetBlockySupplierConfig(StyksBlockySupplerConfig),
+    GrantRole {
+        contract: StyksContract,
+        role: Role,
+        account: Address,
+    },
+    RevokeRole {
+        contract: StyksContract,
+        role: Role,
+        account: Address,
+    },
+    AddFeedMapping {
+        source_key: SourceKey,
+        price_feed_id: PriceFeedId,
+    },
+    RemoveFeedMapping {
+        source_key: SourceKey,
+    },
+}
+
+// Addresses of the contracts the actions are executed against.
+#[odra::odra_type]
+pub struct StyksContracts {
+    pub price_feed: Address,
+    pub blocky_supplier: Address,
+}
+
+impl StyksAdminAction {
+    pub fn execute(&self, env: Rc<ContractEnv>, contracts: &StyksContracts) {
+        use StyksAdminAction::*;
+        match self {
+            SetPriceFeedConfig(config) => {
+                StyksPriceFeedContractRef::new(env, contracts.price_feed)
+                    .set_config(config.clone());
+            }
+            SetBlockySupplierConfig(config) => {
+                StyksBlockySupplierContractRef::new(env, contracts.blocky_supplier)
+                    .set_config(config.clone());
+            }
+            GrantRole {
+                contract,
+                role,
+                account,
+            } => match contract {
+                StyksContract::PriceFeed => {
+                    StyksPriceFeedContractRef::new(env, contracts.price_feed)
+                        .grant_role(role, account);
+                }
+                StyksContract::BlockySupplier => {
+                    StyksBlockySupplierContractRef::new(env, contracts.blocky_supplier)
+                        .grant_role(role, account);
+                }
+            },
+            RevokeRole {
+                contract,
+                role,
+                account,
+            } => match contract {
+                StyksContract::PriceFeed => {
+                    StyksPriceFeedContractRef::new(env, contracts.price_feed)
+                        .revoke_role(role, account);
+                }
+                StyksContract::BlockySupplier => {
+                    StyksBlockySupplierContractRef::new(env, contracts.blocky_supplier)
+                        .revoke_role(role, account);
+                }
+            },
+            AddFeedMapping {
+                source_key,
+                price_feed_id,
+            } => {
+                StyksBlockySupplierContractRef::new(env, contracts.blocky_supplier)
+                    .add_feed_mapping(source_key.clone(), price_feed_id.clone());
+            }
+            RemoveFeedMapping { source_key } => {
+                StyksBlockySupplierContractRef::new(env, contracts.blocky_supplier)
+                    .remove_feed_mapping(source_key.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use odra::{casper_types::bytesrepr::Bytes, prelude::*};
+use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
+use styks_blocky_parser::{
+    address_balance::AddressBalanceOutputValue,
+    blocky_claims::{BlockyClaims, BlockyClaimsError},
+    verify::VerificationError,
+};
+
+use crate::attestation::{self, AttestationError};
+
+// --- Errors ---
+
+#[odra::odra_error]
+pub enum StyksBalanceFeedError {
+    // Config errors.
+    ConfigNotSet = 50000,
+    AddressNotWatched = 50001,
+    WasmHashIsEmpty = 50002,
+    WasmHashNotHex = 50003,
+    TimestampToleranceIsZero = 50004,
+    UnsupportedChain = 50005,
+
+    // Role errors.
+    NotAdminRole = 50100,
+    NotConfigManagerRole = 50101,
+
+    // Verification errors.
+    InvalidPublicKey = 50200,
+    InvalidSignature = 50201,
+    HashingError = 50202,
+    BadSignature = 50203,
+    BadWasmHash = 50204,
+    TimestampOutOfRange = 50205,
+    StaleBalance = 50206,
+    InvalidRecoveryId = 50207,
+    PublicKeyRecoveryFailed = 50208,
+    InvalidSignerAddress = 50209,
+
+    // Claims errors.
+    TADataDecoding = 50300,
+    TADataInvalidLength = 50301,
+    BytesConversionError = 50302,
+    OutputJsonDecoding = 50303,
+    OutputHasNoSuccessStatus = 50304,
+}
+
+impl From<VerificationError> for StyksBalanceFeedError {
+    fn from(error: VerificationError) -> Self {
+        use VerificationError::*;
+        match error {
+            InvalidPublicKey => StyksBalanceFeedError::InvalidPublicKey,
+            InvalidSignature => StyksBalanceFeedError::InvalidSignature,
+            HashingError => StyksBalanceFeedError::HashingError,
+            BadSignature => StyksBalanceFeedError::BadSignature,
+            InvalidRecoveryId => StyksBalanceFeedError::InvalidRecoveryId,
+            PublicKeyRecoveryFailed => StyksBalanceFeedError::PublicKeyRecoveryFailed,
+            InvalidSignerAddress => StyksBalanceFeedError::InvalidSignerAddress,
+        }
+    }
+}
+
+impl From<AttestationError> for StyksBalanceFeedError {
+    fn from(error: AttestationError) -> Self {
+        use AttestationError::*;
+        match error {
+            WasmHashIsEmpty => StyksBalanceFeedError::WasmHashIsEmpty,
+            WasmHashNotHex => StyksBalanceFeedError::WasmHashNotHex,
+            TimestampToleranceIsZero => StyksBalanceFeedError::TimestampToleranceIsZero,
+            BadWasmHash => StyksBalanceFeedError::BadWasmHash,
+            TimestampOutOfRange => StyksBalanceFeedError::TimestampOutOfRange,
+            Verification(error) => error.into(),
+        }
+    }
+}
+
+impl From<BlockyClaimsError> for StyksBalanceFeedError {
+    fn from(error: BlockyClaimsError) -> Self {
+        use BlockyClaimsError::*;
+        match error {
+            TADataDecoding => StyksBalanceFeedError::TADataDecoding,
+            TADataInvalidLength => StyksBalanceFeedError::TADataInvalidLength,
+            BytesConversionError => StyksBalanceFeedError::BytesConversionError,
+            OutputJsonDecoding => StyksBalanceFeedError::OutputJsonDecoding,
+            GuestProgramFailed(_) => StyksBalanceFeedError::OutputHasNoSuccessStatus,
+        }
+    }
+}
+
+// --- Access Control Roles ---
+
+#[derive(Debug)]
+pub enum StyksBalanceFeedRole {
+    Admin,
+    ConfigManager,
+}
+
+impl StyksBalanceFeedRole {
+    pub fn role_id(&self) -> Role {
+        match self {
+            StyksBalanceFeedRole::Admin => DEFAULT_ADMIN_ROLE,
+            // start with 6, so it doesn't overlap with other Styks contracts.
+            StyksBalanceFeedRole::ConfigManager => [6u8; 32],
+        }
+    }
+}
+
+// --- Configuration ---
+
+// Balances are stored as `u64` satoshis, which can't hold wei-scale balances
+// of other chains.
+pub const SUPPORTED_CHAIN: &str = "bitcoin";
+
+#[odra::odra_type]
+pub struct WatchedAddress {
+    pub chain: String,
+    pub address: String,
+}
+
+#[odra::odra_type]
+pub struct StyksBalanceFeedConfig {
+    pub wasm_hash: String,
+    pub public_key: Bytes,
+    pub watched_addresses: Vec<WatchedAddress>,
+    pub timestamp_tolerance: u64,
+}
+
+impl StyksBalanceFeedConfig {
+    pub fn validate(&self) -> Result<(), StyksBalanceFeedError> {
+        attestation::validate_wasm_hash(&self.wasm_hash)?;
+        attestation::validate_public_key(self.public_key())?;
+        attestation::validate_timestamp_tolerance(self.timestamp_tolerance)?;
+        if self.watched_addresses.iter().any(|watched| watched.chain != SUPPORTED_CHAIN) {
+            return Err(StyksBalanceFeedError::UnsupportedChain);
+        }
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn is_watched(&self, chain: &str, address: &str) -> bool {
+        self.watched_addresses
+            .iter()
+            .any(|watched| watched.chain == chain && watched.address == address)
+    }
+}
+
+// --- Balances ---
+
+#[odra::odra_type]
+pub struct AddressBalance {
+    pub balance: u64,
+    pub block_height: u64,
+    pub timestamp: u64,
+}
+
+// --- StyksBalanceFeed Contract ---
+
+// Stores the latest attested balance of watched addresses on other chains,
+// for example to build a BTC <-> CSPR escrow.
+#[odra::module]
+pub struct StyksBalanceFeed {
+    access_control: SubModule<AccessControl>,
+    config: Var<StyksBalanceFeedConfig>,
+    balances: Mapping<(String, String), AddressBalance>, // (chain, address)
+}
+
+#[odra::module]
+impl StyksBalanceFeed {
+    pub fn init(&mut self) {
+        // Grant the admin role to the contract deployer.
+        let deployer = self.env().caller();
+        let admin_role = StyksBalanceFeedRole::Admin.role_id();
+        self.access_control
+            .unchecked_grant_role(&admin_role, &deployer);
+    }
+
+    delegate! {
+        to self.access_control {
+            fn has_role(&self, role: &Role, address: &Address) -> bool;
+            fn grant_role(&mut self, role: &Role, address: &Address);
+            fn revoke_role(&mut self, role: &Role, address: &Address);
+            fn get_role_admin(&self, role: &Role) -> Role;
+            fn renounce_role(&mut self, role: &Role, address: &Address);
+        }
+    }
+
+    pub fn set_config(&mut self, config: StyksBalanceFeedConfig) {
+        // Make sure only ConfigManager can set the config.
+        self.assert_config_manager(&self.env().caller());
+
+        // Validate the config.
+        config.validate().unwrap_or_revert(&self.env());
+
+        // Update the config.
+        self.config.set(config);
+    }
+
+    pub fn get_config(&self) -> StyksBalanceFeedConfig {
+        self.config
+            .get()
+            .unwrap_or_revert_with(&self.env(), StyksBalanceFeedError::ConfigNotSet)
+    }
+
+    pub fn get_config_or_none(&self) -> Option<StyksBalanceFeedConfig> {
+        self.config.get()
+    }
+
+    pub fn get_balance(&self, chain: String, address: String) -> Option<AddressBalance> {
+        self.balances.get(&(chain, address))
+    }
+
+    /// Verifies the signed balance and stores it if it is newer than the current one.
+    pub fn report_signed_balance(&mut self, signature: Bytes, data: Bytes) {
+        let config = self.get_config();
+
+        // Verify the signature.
+        attestation::check_signature(config.public_key(), None, &signature, &data)
+            .map_err(StyksBalanceFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Decode the data.
+        let claims = BlockyClaims::decode_fn_call_claims(&data)
+            .map_err(StyksBalanceFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Verify the claims.
+        attestation::check_wasm_hash(&config.wasm_hash, &claims.hash_of_code())
+            .map_err(StyksBalanceFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Extract the output.
+        let output: AddressBalanceOutputValue = claims
+            .output_as()
+            .map_err(StyksBalanceFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Verify the address is watched.
+        if !config.is_watched(&output.chain, &output.address) {
+            self.env().revert(StyksBalanceFeedError::AddressNotWatched);
+        }
+
+        // Verify the timestamp.
+        let current_time = self.env().get_block_time_secs();
+        attestation::check_timestamp(output.timestamp, current_time, config.timestamp_tolerance)
+            .map_err(StyksBalanceFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Only newer blocks can update the balance.
+        let key = (output.chain, output.address);
+        if let Some(current) = self.balances.get(&key) {
+            if current.block_height >= output.block_height {
+                self.env().revert(StyksBalanceFeedError::StaleBalance);
+            }
+        }
+
+        self.balances.set(
+            &key,
+            AddressBalance {
+                balance: output.balance,
+                block_height: output.block_height,
+                timestamp: output.timestamp,
+            },
+        );
+    }
+}
+
+impl StyksBalanceFeed {
+    fn assert_role(&self, address: &Address, role: StyksBalanceFeedRole) {
+        if !self.has_role(&role.role_id(), address) {
+            use StyksBalanceFeedError::*;
+            use StyksBalanceFeedRole::*;
+            let error = match role {
+                Admin => NotAdminRole,
+                ConfigManager => NotConfigManagerRole,
+            };
+            self.env().revert(error);
+        }
+    }
+
+    fn assert_config_manager(&self, address: &Address) {
+        self.assert_role(address, StyksBalanceFeedRole::ConfigManager);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use odra::host::{Deployer, HostEnv, NoArgs};
+    use styks_blocky_parser::blocky_output::BlockyOutput;
+
+    use crate::attestation::{assert_rejected_configs, Breaker};
+
+    use super::*;
+
+    const ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+    fn load(name: &str) -> BlockyOutput {
+        BlockyOutput::try_from_file(format!("../resources/test/{}", name))
+            .expect("Failed to load BlockyOutput")
+    }
+
+    fn report(contract: &mut StyksBalanceFeedHostRef, output: &BlockyOutput) -> OdraResult<()> {
+        let ta = output.ta().unwrap();
+        contract.try_report_signed_balance(
+            Bytes::from(ta.signature_bytes()),
+            Bytes::from(ta.data().to_vec()),
+        )
+    }
+
+    // The config accepts the recorded balance attestations.
+    fn setup() -> (HostEnv, StyksBalanceFeedHostRef, StyksBalanceFeedConfig) {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let output = load("btc_balance_1_out.json");
+
+        let mut contract = StyksBalanceFeed::deploy(&env, NoArgs);
+        let config = StyksBalanceFeedConfig {
+            wasm_hash: output.transitive_attested_function_call.claims.hash_of_code.clone(),
+            public_key: Bytes::from(output.public_key_bytes().unwrap()),
+            watched_addresses: vec![WatchedAddress {
+                chain: String::from("bitcoin"),
+                address: String::from(ADDRESS),
+            }],
+            timestamp_tolerance: 60,
+        };
+        contract.grant_role(&StyksBalanceFeedRole::ConfigManager.role_id(), &admin);
+        contract.set_config(config.clone());
+        (env, contract, config)
+    }
+
+    #[test]
+    fn test_report_signed_balance() {
+        let (env, mut contract, _) = setup();
+        let (chain, address) = (String::from("bitcoin"), String::from(ADDRESS));
+        assert_eq!(contract.get_balance(chain.clone(), address.clone()), None);
+
+        // Report the first balance.
+        env.advance_block_time(1760000000 * 1000);
+        let first = load("btc_balance_1_out.json");
+        report(&mut contract, &first).unwrap();
+        let expected = AddressBalance {
+            balance: 150_000_000,
+            block_height: 850_000,
+            timestamp: 1760000000,
+        };
+        assert_eq!(contract.get_balance(chain.clone(), address.clone()), Some(expected));
+
+        // Report the newer balance.
+        env.advance_block_time(3600 * 1000);
+        let second = load("btc_balance_2_out.json");
+        report(&mut contract, &second).unwrap();
+        let expected = AddressBalance {
+            balance: 90_000_000,
+            block_height: 850_006,
+            timestamp: 1760003600,
+        };
+        assert_eq!(contract.get_balance(chain.clone(), address.clone()), Some(expected));
+
+        // The same attestation can't be reported twice.
+        let result = report(&mut contract, &second);
+        assert_eq!(result, Err(StyksBalanceFeedError::StaleBalance.into()));
+    }
+
+    #[test]
+    fn test_report_errors() {
+        let (env, mut contract, config) = setup();
+        let output = load("btc_balance_1_out.json");
+
+        // Too old for the current block time.
+        env.advance_block_time(1760000061 * 1000);
+        let result = report(&mut contract, &output);
+        assert_eq!(result, Err(StyksBalanceFeedError::TimestampOutOfRange.into()));
+
+        // Address is not watched.
+        let mut unwatched = config.clone();
+        unwatched.watched_addresses = vec![];
+        contract.set_config(unwatched);
+        let result = report(&mut contract, &output);
+        assert_eq!(result, Err(StyksBalanceFeedError::AddressNotWatched.into()));
+
+        // Different guest program.
+        let mut other_program = config.clone();
+        other_program.wasm_hash = String::from("0123");
+        contract.set_config(other_program);
+        let result = report(&mut contract, &output);
+        assert_eq!(result, Err(StyksBalanceFeedError::BadWasmHash.into()));
+
+        // Different signer.
+        let mut other_signer = config;
+        other_signer.public_key = Bytes::from(load("2_out.json").public_key_bytes().unwrap());
+        contract.set_config(other_signer);
+        let result = report(&mut contract, &output);
+        assert_eq!(result, Err(StyksBalanceFeedError::BadSignature.into()));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let (_env, mut contract, config) = setup();
+        let cases: Vec<(Breaker<StyksBalanceFeedConfig>, StyksBalanceFeedError)> = vec![
+            (|c| c.wasm_hash = String::new(), StyksBalanceFeedError::WasmHashIsEmpty),
+            (|c| c.wasm_hash = String::from("xyz0"), StyksBalanceFeedError::WasmHashNotHex),
+            (|c| c.public_key = Bytes::from(vec![4u8; 65]), StyksBalanceFeedError::InvalidPublicKey),
+            (|c| c.timestamp_tolerance = 0, StyksBalanceFeedError::TimestampToleranceIsZero),
+            (|c| c.watched_addresses[0].chain = String::from("ethereum"), StyksBalanceFeedError::UnsupportedChain),
+        ];
+        assert_rejected_configs(&config, cases, |config| contract.try_set_config(config));
+    }
+}
@@ -1,9 +1,16 @@
 use odra::{casper_types::bytesrepr::Bytes, prelude::*, ContractRef};
 use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
-use styks_blocky_parser::{blocky_claims::{BlockyClaims, BlockyClaimsError}, verify::VerificationError};
-use styks_core::{Price, PriceFeedId};
+use styks_blocky_parser::{
+    blocky_claims::{BlockyClaims, BlockyClaimsError, GuestProgramOutputValue},
+    verify::VerificationError,
+};
+use styks_core::{
+    heartbeat::{Heartbeat, HeartbeatError},
+    quorum::{Quorum, QuorumError},
+    Price, PriceFeedId, SourceKey,
+};
 
-use crate::styks_price_feed::StyksPriceFeedContractRef;
+use crate::{attestation::{self, AttestationError}, styks_price_feed::StyksPriceFeedContractRef};
 
 // --- Errors ---
 
@@ -12,10 +19,18 @@ pub enum StyksBlockySupplerError {
     // Config errors.
     ConfigNotSet = 46000,
     PriceFeedIdNotFound = 46001,
+    InvalidExpectation = 46002,
+    FeedMappingNotFound = 46003,
+    WasmHashIsEmpty = 46004,
+    WasmHashNotHex = 46005,
+    TimestampToleranceIsZero = 46006,
+    ExpectationNotUnique = 46007,
+    PriceFeedIdNotInFeed = 46008,
 
      // Role errors.
     NotAdminRole = 46100,
     NotConfigManagerRole = 46101,
+    NotPriceProducerRole = 46102,
 
     // Verification errors.
     InvalidPublicKey = 46200,
@@ -24,6 +39,9 @@ pub enum StyksBlockySupplerError {
     BadSignature = 46203,
     BadWasmHash = 46204,
     TimestampOutOfRange = 46205,
+    InvalidRecoveryId = 46206,
+    PublicKeyRecoveryFailed = 46207,
+    InvalidSignerAddress = 46208,
 
     // Claims errors.
     TADataDecoding = 46300,
@@ -32,6 +50,25 @@ pub enum StyksBlockySupplerError {
     OutputJsonDecoding = 46303,
     OutputHasNoSuccessStatus = 46304,
 
+    // Quorum errors.
+    QuorumNotEnabled = 46400,
+    QuorumThresholdCannotBeZero = 46401,
+    QuorumToleranceTooHigh = 46402,
+    QuorumNotReached = 46403,
+    NotInHeartbeatWindow = 46404,
+    InvalidHeartbeatConfig = 46405,
+    RoundNotFound = 46406,
+    RoundAlreadyFinalized = 46407,
+    ProducerAlreadyReported = 46408,
+    QuorumThresholdAboveProducers = 46409,
+
+    // Output errors.
+    PriceIsZero = 46500,
+    UnexpectedMarket = 46501,
+    UnexpectedCoinId = 46502,
+    UnexpectedCurrency = 46503,
+    PriceBelowMinimum = 46504,
+    PriceAboveMaximum = 46505,
 }
 
 impl From<VerificationError> for StyksBlockySupplerError {
@@ -42,6 +79,23 @@ impl From<VerificationError> for StyksBlockySupplerError {
             InvalidSignature => StyksBlockySupplerError::InvalidSignature,
             HashingError => StyksBlockySupplerError::HashingError,
             BadSignature => StyksBlockySupplerError::BadSignature,
+            InvalidRecoveryId => StyksBlockySupplerError::InvalidRecoveryId,
+            PublicKeyRecoveryFailed => StyksBlockySupplerError::PublicKeyRecoveryFailed,
+            InvalidSignerAddress => StyksBlockySupplerError::InvalidSignerAddress,
+        }
+    }
+}
+
+impl From<AttestationError> for StyksBlockySupplerError {
+    fn from(error: AttestationError) -> Self {
+        use AttestationError::*;
+        match error {
+            WasmHashIsEmpty => StyksBlockySupplerError::WasmHashIsEmpty,
+            WasmHashNotHex => StyksBlockySupplerError::WasmHashNotHex,
+            TimestampToleranceIsZero => StyksBlockySupplerError::TimestampToleranceIsZero,
+            BadWasmHash => StyksBlockySupplerError::BadWasmHash,
+            TimestampOutOfRange => StyksBlockySupplerError::TimestampOutOfRange,
+            Verification(error) => error.into(),
         }
     }
 }
@@ -54,17 +108,34 @@ impl From<BlockyClaimsError> for StyksBlockySupplerError {
             TADataInvalidLength => StyksBlockySupplerError::TADataInvalidLength,
             BytesConversionError => StyksBlockySupplerError::BytesConversionError,
             OutputJsonDecoding => StyksBlockySupplerError::OutputJsonDecoding,
-            OutputHasNoSuccessStatus => StyksBlockySupplerError::OutputHasNoSuccessStatus,
+            GuestProgramFailed(_) => StyksBlockySupplerError::OutputHasNoSuccessStatus,
         }
     }
 }
 
+impl From<QuorumError> for StyksBlockySupplerError {
+    fn from(error: QuorumError) -> Self {
+        use QuorumError::*;
+        match error {
+            ThresholdCannotBeZero => StyksBlockySupplerError::QuorumThresholdCannotBeZero,
+            ToleranceTooHigh => StyksBlockySupplerError::QuorumToleranceTooHigh,
+        }
+    }
+}
+
+impl From<HeartbeatError> for StyksBlockySupplerError {
+    fn from(_: HeartbeatError) -> Self {
+        StyksBlockySupplerError::InvalidHeartbeatConfig
+    }
+}
+
 // --- Access Control Roles ---
 
 #[derive(Debug)]
 pub enum StyksBlockySupplerRole {
     Admin,
-    ConfigManager
+    ConfigManager,
+    PriceProducer,
 }
 
 impl StyksBlockySupplerRole {
@@ -73,6 +144,7 @@ impl StyksBlockySupplerRole {
             StyksBlockySupplerRole::Admin => DEFAULT_ADMIN_ROLE,
             // start with 3, so it doesn't overlap with PriceFeed.
             StyksBlockySupplerRole::ConfigManager => [3u8; 32],
+            StyksBlockySupplerRole::PriceProducer => [4u8; 32],
         }
     }
 }
@@ -83,21 +155,180 @@ impl StyksBlockySupplerRole {
 pub struct StyksBlockySupplerConfig {
     pub wasm_hash: String,
     pub public_key: Bytes,
-    pub coingecko_feed_ids: Vec<(String, PriceFeedId)>, // (coingecko_id, price_feed_id)
     pub price_feed_address: Address,
     pub timestamp_tolerance: u64,
+    pub quorum: Option<StyksBlockySupplerQuorumConfig>,
+    // When set, the signer is recovered from the 65 bytes signature and
+    // compared with this 20 bytes Ethereum-style address. `public_key` is
+    // not used then.
+    pub signer_address: Option<Bytes>,
+    // Expected guest output per PriceFeedId. Reports for feeds without an
+    // expectation are only checked for a non-zero price.
+    pub expectations: Vec<StyksBlockySupplerExpectation>,
+}
+
+// What the guest program has to report to update the given feed, on top of
+// the feed mapping of its `SourceKey`.
+#[odra::odra_type]
+pub struct StyksBlockySupplerExpectation {
+    pub price_feed_id: PriceFeedId,
+    pub market: String,
+    pub coin_id: String,
+    pub currency: String,
+    pub min_price: u64,
+    pub max_price: u64,
+}
+
+impl StyksBlockySupplerExpectation {
+    pub fn check(&self, output: &GuestProgramOutputValue) -> Result<(), StyksBlockySupplerError> {
+        if output.market != self.market {
+            return Err(StyksBlockySupplerError::UnexpectedMarket);
+        }
+        if output.coin_id != self.coin_id {
+            return Err(StyksBlockySupplerError::UnexpectedCoinId);
+        }
+        if output.currency != self.currency {
+            return Err(StyksBlockySupplerError::UnexpectedCurrency);
+        }
+        if output.price < self.min_price {
+            return Err(StyksBlockySupplerError::PriceBelowMinimum);
+        }
+        if output.price > self.max_price {
+            return Err(StyksBlockySupplerError::PriceAboveMaximum);
+        }
+        Ok(())
+    }
+}
+
+// When set, reports are buffered per heartbeat window and only the median of
+// `threshold` agreeing reports from distinct producers is posted to the feed.
+// The threshold can't exceed the number of PriceProducer holders.
+#[odra::odra_type]
+pub struct StyksBlockySupplerQuorumConfig {
+    pub threshold: u32,
+    pub tolerance_bps: u32,
+}
+
+impl StyksBlockySupplerQuorumConfig {
+    pub fn quorum(&self) -> Result<Quorum, QuorumError> {
+        Quorum::new(self.threshold, self.tolerance_bps)
+    }
+}
+
+// --- Quorum Rounds ---
+
+#[odra::odra_type]
+pub enum QuorumRoundStatus {
+    Pending,
+    Committed,
+    Missed,
+}
+
+#[odra::odra_type]
+pub struct QuorumReport {
+    pub producer: Address,
+    pub price_feed_id: PriceFeedId,
+    pub price: Price,
+}
+
+#[odra::odra_type]
+pub struct QuorumRound {
+    pub status: QuorumRoundStatus,
+    pub reports: Vec<QuorumReport>,
+}
+
+impl QuorumRound {
+    // A producer reports each PriceFeedId of a round once.
+    pub fn has_report_from(&self, producer: &Address, price_feed_id: &PriceFeedId) -> bool {
+        self.reports
+            .iter()
+            .any(|report| &report.producer == producer && &report.price_feed_id == price_feed_id)
+    }
+
+    pub fn prices_for(&self, price_feed_id: &PriceFeedId) -> Vec<Price> {
+        self.reports
+            .iter()
+            .filter(|report| &report.price_feed_id == price_feed_id)
+            .map(|report| report.price)
+            .collect()
+    }
 }
 
 impl StyksBlockySupplerConfig {
+    // Checks the config on its own. PriceFeedIds are checked against the
+    // feed by the contract.
+    pub fn validate(&self) -> Result<(), StyksBlockySupplerError> {
+        attestation::validate_wasm_hash(&self.wasm_hash)?;
+
+        // Only the key that is going to be used has to be valid.
+        match &self.signer_address {
+            Some(signer) if signer.len() != 20 => {
+                return Err(StyksBlockySupplerError::InvalidSignerAddress);
+            }
+            Some(_) => {}
+            None => attestation::validate_public_key(self.public_key())?,
+        }
+
+        attestation::validate_timestamp_tolerance(self.timestamp_tolerance)?;
+
+        if let Some(quorum) = &self.quorum {
+            quorum.quorum()?;
+        }
+
+        // At most one expectation per feed, with valid price bounds.
+        let mut seen_ids = BTreeMap::new();
+        for expectation in &self.expectations {
+            if expectation.min_price == 0 || expectation.min_price > expectation.max_price {
+                return Err(StyksBlockySupplerError::InvalidExpectation);
+            }
+            if seen_ids.insert(expectation.price_feed_id.clone(), ()).is_some() {
+                return Err(StyksBlockySupplerError::ExpectationNotUnique);
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn public_key(&self) -> &[u8] {
         &self.public_key
     }
 
-    pub fn price_feed_id(&self, coingecko_id: &str) -> Option<PriceFeedId> {
-        self.coingecko_feed_ids
+    pub fn expectation(&self, price_feed_id: &PriceFeedId) -> Option<&StyksBlockySupplerExpectation> {
+        self.expectations
             .iter()
-            .find(|(id, _)| id == coingecko_id)
-            .map(|(_, feed_id)| feed_id.clone())
+            .find(|expectation| &expectation.price_feed_id == price_feed_id)
+    }
+
+    // Checks of a report, shared by the contract and the offline verifier.
+
+    pub fn check_signature(&self, signature: &[u8], data: &[u8]) -> Result<(), StyksBlockySupplerError> {
+        let signer = self.signer_address.as_deref();
+        attestation::check_signature(self.public_key(), signer, signature, data)?;
+        Ok(())
+    }
+
+    pub fn check_wasm_hash(&self, hash_of_code: &str) -> Result<(), StyksBlockySupplerError> {
+        attestation::check_wasm_hash(&self.wasm_hash, hash_of_code)?;
+        Ok(())
+    }
+
+    pub fn check_timestamp(&self, reported: u64, current_time: u64) -> Result<(), StyksBlockySupplerError> {
+        attestation::check_timestamp(reported, current_time, self.timestamp_tolerance)?;
+        Ok(())
+    }
+
+    pub fn check_output(
+        &self,
+        price_feed_id: &PriceFeedId,
+        output: &GuestProgramOutputValue,
+    ) -> Result<(), StyksBlockySupplerError> {
+        if output.price == 0 {
+            return Err(StyksBlockySupplerError::PriceIsZero);
+        }
+        match self.expectation(price_feed_id) {
+            Some(expectation) => expectation.check(output),
+            None => Ok(()),
+        }
     }
 }
 
@@ -107,6 +338,15 @@ impl StyksBlockySupplerConfig {
 pub struct StyksBlockySupplier {
     access_control: SubModule<AccessControl>,
     config: Var<StyksBlockySupplerConfig>,
+    // Quorum rounds indexed by the heartbeat time.
+    rounds: Mapping<u64, QuorumRound>,
+    // PriceFeedIds indexed by the source of the price. Removed mappings are
+    // set to `None`, as `Mapping` has no removal.
+    feed_ids: Mapping<SourceKey, Option<PriceFeedId>>,
+    // Keys of `feed_ids`, so producers can list the mappings.
+    source_keys: Var<Vec<SourceKey>>,
+    // Number of PriceProducer holders, the M of the N of M quorum.
+    producer_count: Var<u32>,
 }
 
 #[odra::module]
@@ -122,17 +362,55 @@ impl StyksBlockySupplier {
     delegate! {
         to self.access_control {
             fn has_role(&self, role: &Role, address: &Address) -> bool;
-            fn grant_role(&mut self, role: &Role, address: &Address);
-            fn revoke_role(&mut self, role: &Role, address: &Address);
             fn get_role_admin(&self, role: &Role) -> Role;
-            fn renounce_role(&mut self, role: &Role, address: &Address);
         }
     }
 
+    // Role changes keep track of the PriceProducer holders, so the quorum
+    // threshold stays reachable.
+
+    pub fn grant_role(&mut self, role: &Role, address: &Address) {
+        let had_role = self.has_role(role, address);
+        self.access_control.grant_role(role, address);
+        if !had_role && self.is_producer_role(role) {
+            self.producer_count.set(self.get_producer_count() + 1);
+        }
+    }
+
+    pub fn revoke_role(&mut self, role: &Role, address: &Address) {
+        let had_role = self.has_role(role, address);
+        self.access_control.revoke_role(role, address);
+        if had_role && self.is_producer_role(role) {
+            self.remove_producer();
+        }
+    }
+
+    pub fn renounce_role(&mut self, role: &Role, address: &Address) {
+        let had_role = self.has_role(role, address);
+        self.access_control.renounce_role(role, address);
+        if had_role && self.is_producer_role(role) {
+            self.remove_producer();
+        }
+    }
+
+    pub fn get_producer_count(&self) -> u32 {
+        self.producer_count.get_or_default()
+    }
+
     pub fn set_config(&mut self, config: StyksBlockySupplerConfig) {
         // Make sure only ConfigManager can set the config.
         self.assert_config_manager(&self.env().caller());
 
+        // Validate the config.
+        config.validate().unwrap_or_revert(&self.env());
+        self.assert_quorum_reachable(&config, self.get_producer_count());
+
+        // Make sure the feed knows all the PriceFeedIds.
+        let mapped_ids = self.get_feed_mappings().into_iter().map(|(_, id)| id);
+        let expected_ids = config.expectations.iter().map(|e| e.price_feed_id.clone());
+        let ids: Vec<PriceFeedId> = mapped_ids.chain(expected_ids).collect();
+        self.assert_known_price_feed_ids(&config, &ids);
+
         // Update the config.
         self.config.set(config);
     }
@@ -147,6 +425,44 @@ impl StyksBlockySupplier {
         self.config.get()
     }
 
+    /// Adds or replaces the PriceFeedId the source reports to.
+    pub fn add_feed_mapping(&mut self, source_key: SourceKey, price_feed_id: PriceFeedId) {
+        self.assert_config_manager(&self.env().caller());
+        let config = self.get_config();
+        self.assert_known_price_feed_ids(&config, core::slice::from_ref(&price_feed_id));
+
+        let mut source_keys = self.source_keys.get_or_default();
+        if !source_keys.contains(&source_key) {
+            source_keys.push(source_key.clone());
+            self.source_keys.set(source_keys);
+        }
+        self.feed_ids.set(&source_key, Some(price_feed_id));
+    }
+
+    pub fn remove_feed_mapping(&mut self, source_key: SourceKey) {
+        self.assert_config_manager(&self.env().caller());
+
+        let mut source_keys = self.source_keys.get_or_default();
+        let Some(index) = source_keys.iter().position(|key| key == &source_key) else {
+            self.env().revert(StyksBlockySupplerError::FeedMappingNotFound);
+        };
+        source_keys.swap_remove(index);
+        self.source_keys.set(source_keys);
+        self.feed_ids.set(&source_key, None);
+    }
+
+    pub fn get_price_feed_id(&self, source_key: SourceKey) -> Option<PriceFeedId> {
+        self.feed_ids.get(&source_key).flatten()
+    }
+
+    pub fn get_feed_mappings(&self) -> Vec<(SourceKey, PriceFeedId)> {
+        self.source_keys
+            .get_or_default()
+            .into_iter()
+            .filter_map(|key| self.get_price_feed_id(key.clone()).map(|id| (key, id)))
+            .collect()
+    }
+
     /// Verifies the signature against the data.
     pub fn report_signed_prices(
         &mut self,
@@ -154,23 +470,104 @@ impl StyksBlockySupplier {
         data: Bytes,
     ) {
         let config = self.get_config();
-        let public_key = config.public_key();
+        let (price_feed_id, price) = self.verify_report(&config, &signature, &data);
+
+        // In quorum mode the report waits for other producers.
+        if let Some(quorum) = &config.quorum {
+            self.add_quorum_report(&config, quorum, price_feed_id, price);
+            return;
+        }
+
+        // Report the price to the feed.
+        self.feed(&config).add_to_feed(vec![(price_feed_id, price)]);
+    }
+
+    /// Verifies the (signature, data) reports and posts their prices to the
+    /// feed at once. The feed needs a price of each of its PriceFeedIds.
+    /// In the quorum mode a batch sent after the round is committed is
+    /// ignored, so late producers don't pay for a revert.
+    pub fn report_signed_prices_batch(&mut self, reports: Vec<(Bytes, Bytes)>) {
+        let config = self.get_config();
+        if config.quorum.is_some() && self.is_current_round_committed(&config) {
+            return;
+        }
+
+        let mut prices: Vec<(PriceFeedId, Price)> = reports
+            .iter()
+            .map(|(signature, data)| self.verify_report(&config, signature, data))
+            .collect();
+
+        if let Some(quorum) = &config.quorum {
+            for (price_feed_id, price) in prices {
+                self.add_quorum_report(&config, quorum, price_feed_id, price);
+            }
+            return;
+        }
+
+        prices.sort();
+        self.feed(&config).add_to_feed(prices);
+    }
+
+    /// Closes the quorum round of the given heartbeat.
+    /// While the heartbeat window is open, commits the agreed price or reverts
+    /// if there is no quorum. Once the window is closed, the round is marked as
+    /// missed, which the feed counts as a missed heartbeat.
+    pub fn finalize_round(&mut self, heartbeat: u64) {
+        let config = self.get_config();
+        let quorum = match &config.quorum {
+            Some(quorum) => quorum.clone(),
+            None => self.env().revert(StyksBlockySupplerError::QuorumNotEnabled),
+        };
+
+        let mut round = self
+            .rounds
+            .get(&heartbeat)
+            .unwrap_or_revert_with(&self.env(), StyksBlockySupplerError::RoundNotFound);
+        if round.status != QuorumRoundStatus::Pending {
+            self.env().revert(StyksBlockySupplerError::RoundAlreadyFinalized);
+        }
 
+        let current_window = self.current_heartbeat_window(&config);
+        if current_window == Some(heartbeat) {
+            if !self.try_commit_round(&config, &quorum, &mut round) {
+                self.env().revert(StyksBlockySupplerError::QuorumNotReached);
+            }
+        } else {
+            round.status = QuorumRoundStatus::Missed;
+        }
+        self.rounds.set(&heartbeat, round);
+    }
+
+    pub fn get_round(&self, heartbeat: u64) -> Option<QuorumRound> {
+        self.rounds.get(&heartbeat)
+    }
+}
+
+impl StyksBlockySupplier {
+    fn feed(&self, config: &StyksBlockySupplerConfig) -> StyksPriceFeedContractRef {
+        StyksPriceFeedContractRef::new(self.env(), config.price_feed_address)
+    }
+
+    // Checks the signed report and returns the PriceFeedId and the price.
+    fn verify_report(
+        &self,
+        config: &StyksBlockySupplerConfig,
+        signature: &[u8],
+        data: &[u8],
+    ) -> (PriceFeedId, Price) {
         // Verify the signature.
-        self.assert_valid_signature(&public_key, &signature, &data);
+        config.check_signature(signature, data).unwrap_or_revert(&self.env());
 
         // Decode the data.
-        let claims = match BlockyClaims::decode_fn_call_claims(&data) {
+        let claims = match BlockyClaims::decode_fn_call_claims(data) {
             Ok(claims) => claims,
             Err(error) => {
                 self.env().revert(StyksBlockySupplerError::from(error));
             }
         };
-        
+
         // Verify the claims.
-        if claims.hash_of_code() != config.wasm_hash {
-            self.env().revert(StyksBlockySupplerError::BadWasmHash);
-        }
+        config.check_wasm_hash(&claims.hash_of_code()).unwrap_or_revert(&self.env());
 
         // Extract the output.
         let output = match claims.output() {
@@ -181,29 +578,110 @@ impl StyksBlockySupplier {
         };
 
         // Verify the timestamp.
-        self.assert_timestamp_in_range(output.timestamp, config.timestamp_tolerance);
-
-        // Load the price feed.
-        let mut feed = StyksPriceFeedContractRef::new(
-            self.env(),
-            config.price_feed_address,
-        );
+        config
+            .check_timestamp(output.timestamp, self.env().get_block_time_secs())
+            .unwrap_or_revert(&self.env());
 
         // Load the price.
         let price = Price::from(output.price);
 
         // Load the PriceFeedId.
-        let price_feed_id = match config.price_feed_id(&output.identifier()) {
-            Some(id) => PriceFeedId::from(id),
+        let source_key = SourceKey::new(&output.market, &output.coin_id, &output.currency);
+        let price_feed_id = match self.get_price_feed_id(source_key) {
+            Some(id) => id,
             None => self.env().revert(StyksBlockySupplerError::PriceFeedIdNotFound)
         };
 
-        // Report the price to the feed.
-        feed.add_to_feed(vec![(price_feed_id, price)]);
+        // Verify the output is what the feed expects.
+        config.check_output(&price_feed_id, &output).unwrap_or_revert(&self.env());
+        (price_feed_id, price)
+    }
+
+    fn add_quorum_report(
+        &mut self,
+        config: &StyksBlockySupplerConfig,
+        quorum: &StyksBlockySupplerQuorumConfig,
+        price_feed_id: PriceFeedId,
+        price: Price,
+    ) {
+        // Only registered producers take part in the quorum.
+        let producer = self.env().caller();
+        self.assert_role(&producer, StyksBlockySupplerRole::PriceProducer);
+
+        // Reports are grouped by the heartbeat window of the feed.
+        let heartbeat = match self.current_heartbeat_window(config) {
+            Some(heartbeat) => heartbeat,
+            None => self.env().revert(StyksBlockySupplerError::NotInHeartbeatWindow),
+        };
+
+        let mut round = self.rounds.get(&heartbeat).unwrap_or(QuorumRound {
+            status: QuorumRoundStatus::Pending,
+            reports: Vec::new(),
+        });
+        if round.status != QuorumRoundStatus::Pending {
+            self.env().revert(StyksBlockySupplerError::RoundAlreadyFinalized);
+        }
+        if round.has_report_from(&producer, &price_feed_id) {
+            self.env().revert(StyksBlockySupplerError::ProducerAlreadyReported);
+        }
+
+        round.reports.push(QuorumReport {
+            producer,
+            price_feed_id,
+            price,
+        });
+
+        // Commit as soon as the quorum is reached.
+        self.try_commit_round(config, quorum, &mut round);
+        self.rounds.set(&heartbeat, round);
+    }
+
+    // Posts the agreed prices to the feed. Returns false if there is no quorum.
+    fn try_commit_round(
+        &self,
+        config: &StyksBlockySupplerConfig,
+        quorum: &StyksBlockySupplerQuorumConfig,
+        round: &mut QuorumRound,
+    ) -> bool {
+        let quorum = quorum
+            .quorum()
+            .map_err(StyksBlockySupplerError::from)
+            .unwrap_or_revert(&self.env());
+
+        // The feed takes prices for all of its PriceFeedIds at once.
+        let price_feed_ids = self.feed(config).get_config().sorted_price_feed_ids();
+        let mut prices = Vec::new();
+        for id in price_feed_ids {
+            match quorum.agreed_price(&round.prices_for(&id)) {
+                Some(price) => prices.push((id, price)),
+                None => return false,
+            }
+        }
+
+        self.feed(config).add_to_feed(prices);
+        round.status = QuorumRoundStatus::Committed;
+        true
+    }
+
+    fn is_current_round_committed(&self, config: &StyksBlockySupplerConfig) -> bool {
+        self.current_heartbeat_window(config)
+            .and_then(|heartbeat| self.rounds.get(&heartbeat))
+            .is_some_and(|round| round.status == QuorumRoundStatus::Committed)
+    }
+
+    // Returns the middle of the current heartbeat window of the feed.
+    fn current_heartbeat_window(&self, config: &StyksBlockySupplerConfig) -> Option<u64> {
+        let feed_config = self.feed(config).get_config();
+        let heartbeat = Heartbeat::new(
+            self.env().get_block_time_secs(),
+            feed_config.heartbeat_interval,
+            feed_config.heartbeat_tolerance,
+        )
+        .map_err(StyksBlockySupplerError::from)
+        .unwrap_or_revert(&self.env());
+        heartbeat.current_state().current.map(|window| window.middle)
     }
-}
 
-impl StyksBlockySupplier {
     fn assert_role(&self, address: &Address, role: StyksBlockySupplerRole) {
         if !self.has_role(&role.role_id(), address) {
             use StyksBlockySupplerError::*;
@@ -211,6 +689,7 @@ impl StyksBlockySupplier {
             let error = match role {
                 Admin => NotAdminRole,
                 ConfigManager => NotConfigManagerRole,
+                PriceProducer => NotPriceProducerRole,
             };
             self.env().revert(error);
         }
@@ -220,26 +699,37 @@ impl StyksBlockySupplier {
         self.assert_role(address, StyksBlockySupplerRole::ConfigManager);
     }
 
-    fn assert_valid_signature(
-        &self,
-        public_key: &[u8],
-        signature: &[u8],
-        data: &[u8],
-    ) {
-        let result = styks_blocky_parser::verify::verify_signature(
-            public_key,
-            signature,
-            data,
-        );
-        if let Err(error) = result {
-            self.env().revert(StyksBlockySupplerError::from(error));
+    fn is_producer_role(&self, role: &Role) -> bool {
+        role == &StyksBlockySupplerRole::PriceProducer.role_id()
+    }
+
+    fn remove_producer(&mut self) {
+        let producer_count = self.get_producer_count().saturating_sub(1);
+        self.producer_count.set(producer_count);
+        if let Some(config) = self.config.get() {
+            self.assert_quorum_reachable(&config, producer_count);
+        }
+    }
+
+    fn assert_quorum_reachable(&self, config: &StyksBlockySupplerConfig, producer_count: u32) {
+        if let Some(quorum) = &config.quorum {
+            if quorum.threshold > producer_count {
+                self.env().revert(StyksBlockySupplerError::QuorumThresholdAboveProducers);
+            }
         }
     }
 
-    fn assert_timestamp_in_range(&self, reported: u64, tolerance: u64) {
-        let current_time = self.env().get_block_time_secs();
-        if reported < current_time.saturating_sub(tolerance) || reported > current_time + tolerance {
-            self.env().revert(StyksBlockySupplerError::TimestampOutOfRange);
+    fn assert_known_price_feed_ids(
+        &self,
+        config: &StyksBlockySupplerConfig,
+        price_feed_ids: &[PriceFeedId],
+    ) {
+        let known_ids = match self.feed(config).get_config_or_none() {
+            Some(feed_config) => feed_config.price_feed_ids,
+            None => Vec::new(),
+        };
+        if price_feed_ids.iter().any(|id| !known_ids.contains(id)) {
+            self.env().revert(StyksBlockySupplerError::PriceFeedIdNotInFeed);
         }
     }
 }
@@ -247,9 +737,16 @@ impl StyksBlockySupplier {
 #[cfg(test)]
 mod tests {
     use odra::{host::{Deployer, HostEnv, NoArgs}};
-    use styks_blocky_parser::blocky_output::BlockyOutput;
+    use styks_blocky_parser::{
+        blocky_output::BlockyOutput,
+        test_utils::{TestAttestation, TestSigner},
+    };
 
-    use crate::styks_price_feed::{StyksPriceFeed, StyksPriceFeedConfig, StyksPriceFeedHostRef, StyksPriceFeedRole};
+    use crate::attestation::{assert_rejected_configs, Breaker};
+    use crate::styks_price_feed::{
+        StyksPriceFeed, StyksPriceFeedConfig, StyksPriceFeedError, StyksPriceFeedHostRef,
+        StyksPriceFeedRole,
+    };
 
     use super::*;
 
@@ -282,15 +779,16 @@ mod tests {
         let mut supplier = StyksBlockySupplier::deploy(&env, NoArgs);
         let supplier_config = StyksBlockySupplerConfig {
             wasm_hash,
-            public_key: Bytes::from(blocky_output.public_key_bytes()),
-            coingecko_feed_ids: vec![
-                (String::from("Gate_CSPR_USD"), String::from("CSPRUSD"))
-            ],
+            public_key: Bytes::from(blocky_output.public_key_bytes().unwrap()),
             price_feed_address: feed.address(),
             timestamp_tolerance: 1, // 1 sec tolerance
+            quorum: None,
+            signer_address: None,
+            expectations: vec![],
         };
         supplier.grant_role(&StyksBlockySupplerRole::ConfigManager.role_id(), &admin);
         supplier.set_config(supplier_config.clone());
+        supplier.add_feed_mapping(SourceKey::new("Gate", "CSPR", "USD"), String::from("CSPRUSD"));
 
         // Allow StyksBlockySupplier to add prices to StyksPriceFeed.
         let role = StyksPriceFeedRole::PriceSupplier.role_id();
@@ -302,7 +800,7 @@ mod tests {
     #[test]
     fn test_styks_blocky_supplier() {
         let (env, feed, mut supplier, supplier_config, blocky_output) = setup();
-        let id = supplier_config.coingecko_feed_ids[0].1.clone();
+        let id = String::from("CSPRUSD");
 
         // Check initial config.
         assert_eq!(supplier.get_config(), supplier_config);
@@ -316,7 +814,7 @@ mod tests {
         assert_eq!(feed.get_twap_price(&id), None);
 
         // Report prices using the supplier.
-        let ta = blocky_output.ta();
+        let ta = blocky_output.ta().unwrap();
         let signature = ta.signature_bytes();
         let data = ta.data();
 
@@ -329,4 +827,509 @@ mod tests {
         let price = feed.get_twap_price(&id);
         assert_eq!(price, Some(1056));
     }
+
+    #[test]
+    fn test_report_errors() {
+        let (env, feed, mut supplier, mut supplier_config, _) = setup();
+        let id = String::from("CSPRUSD");
+        let signer = TestSigner::from_seed(5);
+        supplier_config.public_key = Bytes::from(signer.public_key_bytes());
+        supplier.set_config(supplier_config);
+
+        let timestamp = 1700000000;
+        env.advance_block_time(timestamp * 1000);
+        let report = |supplier: &mut StyksBlockySupplierHostRef, attestation: TestAttestation| {
+            let (signature, data) = attestation.sign(&signer);
+            supplier.try_report_signed_prices(Bytes::from(signature), Bytes::from(data))
+        };
+        let price = |price, timestamp| TestAttestation::price("Gate", "CSPR", "USD", price, timestamp);
+
+        // Different guest program.
+        let result = report(&mut supplier, price(1234, timestamp).with_hash_of_code("other"));
+        assert_eq!(result, Err(StyksBlockySupplerError::BadWasmHash.into()));
+
+        // Too far from the block time.
+        let result = report(&mut supplier, price(1234, timestamp + 2));
+        assert_eq!(result, Err(StyksBlockySupplerError::TimestampOutOfRange.into()));
+        let result = report(&mut supplier, price(1234, timestamp - 2));
+        assert_eq!(result, Err(StyksBlockySupplerError::TimestampOutOfRange.into()));
+
+        // Unknown market.
+        let unknown = TestAttestation::price("Binance", "CSPR", "USD", 1234, timestamp);
+        let result = report(&mut supplier, unknown);
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceFeedIdNotFound.into()));
+
+        // Different signer.
+        let (signature, data) = price(1234, timestamp).sign(&TestSigner::from_seed(6));
+        let result = supplier.try_report_signed_prices(Bytes::from(signature), Bytes::from(data));
+        assert_eq!(result, Err(StyksBlockySupplerError::BadSignature.into()));
+
+        // Failed guest program.
+        let failed = price(1234, timestamp)
+            .with_output(r#"{ "Success": false, "Error": "market Gate not found" , "Value": null }"#);
+        let result = report(&mut supplier, failed);
+        assert_eq!(result, Err(StyksBlockySupplerError::OutputHasNoSuccessStatus.into()));
+
+        // Valid report.
+        report(&mut supplier, price(1234, timestamp + 1)).unwrap();
+        assert_eq!(feed.get_twap_price(&id), Some(1234));
+    }
+
+    #[test]
+    fn test_report_batch() {
+        let (env, mut feed, mut supplier, mut supplier_config, _) = setup();
+        let cspr = String::from("CSPRUSD");
+        let btc = String::from("BTCUSD");
+        let signer = TestSigner::from_seed(5);
+        supplier_config.public_key = Bytes::from(signer.public_key_bytes());
+        supplier.set_config(supplier_config);
+
+        // The feed tracks two PriceFeedIds.
+        let mut feed_config = feed.get_config();
+        feed_config.price_feed_ids = vec![cspr.clone(), btc.clone()];
+        feed.set_config(feed_config);
+        supplier.add_feed_mapping(SourceKey::new("Gate", "BTC", "USD"), btc.clone());
+
+        let timestamp = 1700000000;
+        env.advance_block_time(timestamp * 1000);
+        let report = |coin_id, price| {
+            let attestation = TestAttestation::price("Gate", coin_id, "USD", price, timestamp);
+            let (signature, data) = attestation.sign(&signer);
+            (Bytes::from(signature), Bytes::from(data))
+        };
+
+        // A single report doesn't cover both feeds.
+        let (signature, data) = report("CSPR", 1000);
+        let result = supplier.try_report_signed_prices(signature, data);
+        assert_eq!(result, Err(StyksPriceFeedError::PriceFeedIdsMissmatch.into()));
+        let result = supplier.try_report_signed_prices_batch(vec![report("CSPR", 1000)]);
+        assert_eq!(result, Err(StyksPriceFeedError::PriceFeedIdsMissmatch.into()));
+
+        // Every report of the batch is verified.
+        let mut invalid = report("BTC", 60000);
+        invalid.0 = Bytes::from(vec![0u8; 65]);
+        let result = supplier.try_report_signed_prices_batch(vec![report("CSPR", 1000), invalid]);
+        assert_eq!(result, Err(StyksBlockySupplerError::InvalidSignature.into()));
+
+        // Reports of all feeds are posted together, in any order.
+        supplier.report_signed_prices_batch(vec![report("CSPR", 1000), report("BTC", 60000)]);
+        assert_eq!(feed.get_twap_price(&cspr), Some(1000));
+        assert_eq!(feed.get_twap_price(&btc), Some(60000));
+    }
+
+    #[test]
+    fn test_expectations() {
+        let (env, feed, mut supplier, mut supplier_config, _) = setup();
+        let id = String::from("CSPRUSD");
+        let signer = TestSigner::from_seed(5);
+        supplier_config.public_key = Bytes::from(signer.public_key_bytes());
+        supplier_config.expectations = vec![StyksBlockySupplerExpectation {
+            price_feed_id: id.clone(),
+            market: String::from("Gate"),
+            coin_id: String::from("CSPR"),
+            currency: String::from("USD"),
+            min_price: 100,
+            max_price: 10000,
+        }];
+        supplier.set_config(supplier_config.clone());
+        // More sources resolving to the same feed.
+        for (market, coin_id, currency) in
+            [("Gate_CSPR", "X", "USD"), ("Gate", "CSPR_X", "USD"), ("Gate", "CSPR", "EUR")]
+        {
+            supplier.add_feed_mapping(SourceKey::new(market, coin_id, currency), id.clone());
+        }
+
+        let timestamp = 1700000000;
+        env.advance_block_time(timestamp * 1000);
+        let report = |supplier: &mut StyksBlockySupplierHostRef, market, coin_id, currency, price| {
+            let attestation = TestAttestation::price(market, coin_id, currency, price, timestamp);
+            let (signature, data) = attestation.sign(&signer);
+            supplier.try_report_signed_prices(Bytes::from(signature), Bytes::from(data))
+        };
+
+        let result = report(&mut supplier, "Gate", "CSPR", "USD", 0);
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceIsZero.into()));
+        let result = report(&mut supplier, "Gate", "CSPR", "USD", 99);
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceBelowMinimum.into()));
+        let result = report(&mut supplier, "Gate", "CSPR", "USD", 10001);
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceAboveMaximum.into()));
+        let result = report(&mut supplier, "Gate_CSPR", "X", "USD", 1234);
+        assert_eq!(result, Err(StyksBlockySupplerError::UnexpectedMarket.into()));
+        let result = report(&mut supplier, "Gate", "CSPR_X", "USD", 1234);
+        assert_eq!(result, Err(StyksBlockySupplerError::UnexpectedCoinId.into()));
+
+        // Currency differs from the quote currency of the feed.
+        let result = report(&mut supplier, "Gate", "CSPR", "EUR", 1234);
+        assert_eq!(result, Err(StyksBlockySupplerError::UnexpectedCurrency.into()));
+
+        // Valid report.
+        report(&mut supplier, "Gate", "CSPR", "USD", 1234).unwrap();
+        assert_eq!(feed.get_twap_price(&id), Some(1234));
+
+        // Invalid bounds.
+        supplier_config.expectations[0].min_price = 20000;
+        let result = supplier.try_set_config(supplier_config.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::InvalidExpectation.into()));
+        supplier_config.expectations[0].min_price = 0;
+        let result = supplier.try_set_config(supplier_config);
+        assert_eq!(result, Err(StyksBlockySupplerError::InvalidExpectation.into()));
+    }
+
+    #[test]
+    fn test_feed_mappings() {
+        let (env, feed, mut supplier, _, blocky_output) = setup();
+        let gate = SourceKey::new("Gate", "CSPR", "USD");
+        let gate_io = SourceKey::new("Gate_io", "CSPR", "USD");
+        let csprusd = String::from("CSPRUSD");
+        assert_eq!(supplier.get_price_feed_id(gate.clone()), Some(csprusd.clone()));
+        assert_eq!(supplier.get_price_feed_id(gate_io.clone()), None);
+
+        // Add and replace.
+        supplier.add_feed_mapping(gate_io.clone(), csprusd.clone());
+        supplier.add_feed_mapping(gate_io.clone(), csprusd.clone());
+        assert_eq!(
+            supplier.get_feed_mappings(),
+            vec![(gate.clone(), csprusd.clone()), (gate_io.clone(), csprusd.clone())]
+        );
+
+        // The feed has to know the PriceFeedId.
+        let result = supplier.try_add_feed_mapping(gate_io.clone(), String::from("OTHER"));
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceFeedIdNotInFeed.into()));
+
+        // Remove.
+        supplier.remove_feed_mapping(gate_io.clone());
+        assert_eq!(supplier.get_price_feed_id(gate_io.clone()), None);
+        assert_eq!(supplier.get_feed_mappings(), vec![(gate.clone(), csprusd.clone())]);
+        let result = supplier.try_remove_feed_mapping(gate_io.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::FeedMappingNotFound.into()));
+
+        // Only the config manager can change mappings.
+        env.set_caller(env.get_account(1));
+        let result = supplier.try_add_feed_mapping(gate_io, csprusd.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::NotConfigManagerRole.into()));
+        let result = supplier.try_remove_feed_mapping(gate.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::NotConfigManagerRole.into()));
+        env.set_caller(env.get_account(0));
+
+        // Reports for removed mappings are rejected.
+        env.advance_block_time(1755463157 * 1000);
+        let ta = blocky_output.ta().unwrap();
+        let signature = Bytes::from(ta.signature_bytes());
+        let data = Bytes::from(ta.data().to_vec());
+        supplier.remove_feed_mapping(gate);
+        let result = supplier.try_report_signed_prices(signature, data);
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceFeedIdNotFound.into()));
+        assert_eq!(feed.get_twap_price(&csprusd), None);
+    }
+
+    #[test]
+    fn test_signer_address_mode() {
+        let (env, feed, mut supplier, mut supplier_config, blocky_output) = setup();
+        let id = String::from("CSPRUSD");
+        env.advance_block_time(1755463157 * 1000);
+
+        let ta = blocky_output.ta().unwrap();
+        let signature = Bytes::from(ta.recoverable_signature_bytes());
+        let data = Bytes::from(ta.data().to_vec());
+
+        // Unknown signer.
+        supplier_config.public_key = Bytes::new();
+        supplier_config.signer_address = Some(Bytes::from(vec![1u8; 20]));
+        supplier.set_config(supplier_config.clone());
+        let result = supplier.try_report_signed_prices(signature.clone(), data.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::BadSignature.into()));
+
+        // Pin the signer of the attestation.
+        let public_key = blocky_output.public_key_bytes().unwrap();
+        let signer = styks_blocky_parser::verify::eth_address(&public_key).unwrap();
+        supplier_config.signer_address = Some(Bytes::from(signer.to_vec()));
+        supplier.set_config(supplier_config);
+
+        // The recovery byte is required.
+        let result = supplier.try_report_signed_prices(
+            Bytes::from(ta.signature_bytes()),
+            data.clone(),
+        );
+        assert_eq!(result, Err(StyksBlockySupplerError::InvalidSignature.into()));
+
+        supplier.report_signed_prices(signature, data);
+        assert_eq!(feed.get_twap_price(&id), Some(1056));
+    }
+
+    // Gas used by `report_signed_prices`, with the signature checked by k256
+    // and keccak256 in wasm. OdraVM doesn't meter gas, so run it against the
+    // wasm build:
+    // `cargo odra test -b casper -- --ignored bench_report_signed_prices_gas --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_report_signed_prices_gas() {
+        let (env, _feed, mut supplier, mut supplier_config, blocky_output) = setup();
+        let ta = blocky_output.ta().unwrap();
+        let signature = Bytes::from(ta.recoverable_signature_bytes());
+        let data = Bytes::from(ta.data().to_vec());
+        env.advance_block_time(1755463157 * 1000);
+
+        // Public key mode.
+        supplier.report_signed_prices(signature.clone(), data.clone());
+
+        // Signer address mode, in the next heartbeat.
+        let public_key = blocky_output.public_key_bytes().unwrap();
+        let signer = styks_blocky_parser::verify::eth_address(&public_key).unwrap();
+        supplier_config.signer_address = Some(Bytes::from(signer.to_vec()));
+        supplier_config.timestamp_tolerance = 200;
+        supplier.set_config(supplier_config);
+        env.advance_block_time(100 * 1000);
+        supplier.report_signed_prices(signature, data);
+
+        println!("{}", env.gas_report());
+    }
+
+    fn enable_quorum(
+        env: &HostEnv,
+        supplier: &mut StyksBlockySupplierHostRef,
+        mut config: StyksBlockySupplerConfig,
+        threshold: u32,
+    ) -> Vec<Address> {
+        // Register producers.
+        let role = StyksBlockySupplerRole::PriceProducer.role_id();
+        let producers: Vec<Address> = (1..4).map(|i| env.get_account(i)).collect();
+        for producer in &producers {
+            supplier.grant_role(&role, producer);
+        }
+
+        config.quorum = Some(StyksBlockySupplerQuorumConfig {
+            threshold,
+            tolerance_bps: 100,
+        });
+        supplier.set_config(config);
+        producers
+    }
+
+    #[test]
+    fn test_quorum_commit() {
+        let (env, feed, mut supplier, supplier_config, blocky_output) = setup();
+        let id = String::from("CSPRUSD");
+        let producers = enable_quorum(&env, &mut supplier, supplier_config, 2);
+
+        let timestamp = 1755463157;
+        env.advance_block_time(timestamp * 1000);
+        let heartbeat = 1755463200;
+
+        let ta = blocky_output.ta().unwrap();
+        let signature = Bytes::from(ta.signature_bytes());
+        let data = Bytes::from(ta.data().to_vec());
+
+        // Only producers can report in quorum mode.
+        env.set_caller(env.get_account(5));
+        let result = supplier.try_report_signed_prices(signature.clone(), data.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::NotPriceProducerRole.into()));
+
+        // The first report is buffered.
+        env.set_caller(producers[0]);
+        supplier.report_signed_prices(signature.clone(), data.clone());
+        let round = supplier.get_round(heartbeat).unwrap();
+        assert_eq!(round.status, QuorumRoundStatus::Pending);
+        assert_eq!(round.reports.len(), 1);
+        assert_eq!(feed.get_twap_price(&id), None);
+
+        // The same producer can't report twice.
+        let result = supplier.try_report_signed_prices(signature.clone(), data.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::ProducerAlreadyReported.into()));
+
+        // Finalizing without quorum fails while the window is open.
+        let result = supplier.try_finalize_round(heartbeat);
+        assert_eq!(result, Err(StyksBlockySupplerError::QuorumNotReached.into()));
+
+        // The second report reaches the quorum and commits the price.
+        env.set_caller(producers[1]);
+        supplier.report_signed_prices(signature.clone(), data.clone());
+        let round = supplier.get_round(heartbeat).unwrap();
+        assert_eq!(round.status, QuorumRoundStatus::Committed);
+        assert_eq!(feed.get_twap_price(&id), Some(1056));
+
+        // Late reports are rejected, late batches are ignored.
+        env.set_caller(producers[2]);
+        let result = supplier.try_report_signed_prices(signature.clone(), data.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::RoundAlreadyFinalized.into()));
+        supplier.report_signed_prices_batch(vec![(signature, data)]);
+        assert_eq!(supplier.get_round(heartbeat).unwrap().reports.len(), 2);
+    }
+
+    #[test]
+    fn test_quorum_missed_round() {
+        let (env, feed, mut supplier, supplier_config, blocky_output) = setup();
+        let id = String::from("CSPRUSD");
+        let producers = enable_quorum(&env, &mut supplier, supplier_config, 3);
+
+        let timestamp = 1755463157;
+        env.advance_block_time(timestamp * 1000);
+        let heartbeat = 1755463200;
+
+        let ta = blocky_output.ta().unwrap();
+        let signature = Bytes::from(ta.signature_bytes());
+        let data = Bytes::from(ta.data().to_vec());
+
+        // Two of three required reports.
+        for producer in &producers[..2] {
+            env.set_caller(*producer);
+            supplier.report_signed_prices(signature.clone(), data.clone());
+        }
+
+        // Unknown rounds can't be finalized.
+        let result = supplier.try_finalize_round(heartbeat + 100);
+        assert_eq!(result, Err(StyksBlockySupplerError::RoundNotFound.into()));
+
+        // Close the window without quorum.
+        env.advance_block_time(100 * 1000);
+        supplier.finalize_round(heartbeat);
+        let round = supplier.get_round(heartbeat).unwrap();
+        assert_eq!(round.status, QuorumRoundStatus::Missed);
+        assert_eq!(feed.get_twap_price(&id), None);
+        assert_eq!(feed.get_last_heartbeat(), None);
+
+        // The round can be finalized only once.
+        let result = supplier.try_finalize_round(heartbeat);
+        assert_eq!(result, Err(StyksBlockySupplerError::RoundAlreadyFinalized.into()));
+    }
+
+    #[test]
+    fn test_quorum_several_feeds() {
+        let (env, mut feed, mut supplier, mut supplier_config, _) = setup();
+        let cspr = String::from("CSPRUSD");
+        let btc = String::from("BTCUSD");
+        let signer = TestSigner::from_seed(5);
+        supplier_config.public_key = Bytes::from(signer.public_key_bytes());
+
+        // The feed tracks two PriceFeedIds.
+        let mut feed_config = feed.get_config();
+        feed_config.price_feed_ids = vec![cspr.clone(), btc.clone()];
+        feed.set_config(feed_config);
+        supplier.add_feed_mapping(SourceKey::new("Gate", "BTC", "USD"), btc.clone());
+        let producers = enable_quorum(&env, &mut supplier, supplier_config, 2);
+
+        let timestamp = 1700000000;
+        env.advance_block_time(timestamp * 1000);
+        let report = |supplier: &mut StyksBlockySupplierHostRef, coin_id, price| {
+            let attestation = TestAttestation::price("Gate", coin_id, "USD", price, timestamp);
+            let (signature, data) = attestation.sign(&signer);
+            supplier.try_report_signed_prices(Bytes::from(signature), Bytes::from(data))
+        };
+
+        // A producer reports each feed once.
+        env.set_caller(producers[0]);
+        report(&mut supplier, "CSPR", 1000).unwrap();
+        report(&mut supplier, "BTC", 60000).unwrap();
+        let result = report(&mut supplier, "BTC", 60000);
+        assert_eq!(result, Err(StyksBlockySupplerError::ProducerAlreadyReported.into()));
+
+        // Quorum on one feed only doesn't commit the round.
+        env.set_caller(producers[1]);
+        report(&mut supplier, "CSPR", 1002).unwrap();
+        let round = supplier.get_round(timestamp).unwrap();
+        assert_eq!(round.status, QuorumRoundStatus::Pending);
+        assert_eq!(feed.get_twap_price(&cspr), None);
+
+        // Quorum on both feeds commits both prices.
+        report(&mut supplier, "BTC", 60010).unwrap();
+        let round = supplier.get_round(timestamp).unwrap();
+        assert_eq!(round.status, QuorumRoundStatus::Committed);
+        assert_eq!(feed.get_twap_price(&cspr), Some(1001));
+        assert_eq!(feed.get_twap_price(&btc), Some(60005));
+    }
+
+    #[test]
+    fn test_quorum_config_validation() {
+        let (_env, _feed, mut supplier, mut supplier_config, _) = setup();
+        supplier_config.quorum = Some(StyksBlockySupplerQuorumConfig {
+            threshold: 0,
+            tolerance_bps: 100,
+        });
+        let result = supplier.try_set_config(supplier_config.clone());
+        assert_eq!(result, Err(StyksBlockySupplerError::QuorumThresholdCannotBeZero.into()));
+
+        // Finalizing requires the quorum mode.
+        let result = supplier.try_finalize_round(0);
+        assert_eq!(result, Err(StyksBlockySupplerError::QuorumNotEnabled.into()));
+    }
+
+    #[test]
+    fn test_quorum_producer_count() {
+        let (env, _feed, mut supplier, supplier_config, _) = setup();
+        let role = StyksBlockySupplerRole::PriceProducer.role_id();
+        let producers = enable_quorum(&env, &mut supplier, supplier_config.clone(), 2);
+        assert_eq!(supplier.get_producer_count(), 3);
+
+        // Granting the role twice counts once.
+        supplier.grant_role(&role, &producers[0]);
+        assert_eq!(supplier.get_producer_count(), 3);
+
+        // The threshold can't exceed the number of producers.
+        let mut config = supplier.get_config();
+        config.quorum = Some(StyksBlockySupplerQuorumConfig {
+            threshold: 4,
+            tolerance_bps: 100,
+        });
+        let result = supplier.try_set_config(config);
+        assert_eq!(result, Err(StyksBlockySupplerError::QuorumThresholdAboveProducers.into()));
+
+        // Producers can leave while the threshold stays reachable.
+        supplier.revoke_role(&role, &producers[0]);
+        assert_eq!(supplier.get_producer_count(), 2);
+        let result = supplier.try_revoke_role(&role, &producers[1]);
+        assert_eq!(result, Err(StyksBlockySupplerError::QuorumThresholdAboveProducers.into()));
+        env.set_caller(producers[1]);
+        let result = supplier.try_renounce_role(&role, &producers[1]);
+        assert_eq!(result, Err(StyksBlockySupplerError::QuorumThresholdAboveProducers.into()));
+
+        // Without the quorum mode the count isn't limited.
+        env.set_caller(env.get_account(0));
+        supplier.set_config(supplier_config);
+        supplier.revoke_role(&role, &producers[1]);
+        assert_eq!(supplier.get_producer_count(), 1);
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let (_env, mut feed, mut supplier, supplier_config, _) = setup();
+        let cases: Vec<(Breaker<StyksBlockySupplerConfig>, StyksBlockySupplerError)> = vec![
+            (|c| c.wasm_hash = String::new(), StyksBlockySupplerError::WasmHashIsEmpty),
+            (|c| c.wasm_hash = String::from("xyz0"), StyksBlockySupplerError::WasmHashNotHex),
+            (|c| c.public_key = Bytes::from(vec![4u8; 65]), StyksBlockySupplerError::InvalidPublicKey),
+            (
+                |c| c.signer_address = Some(Bytes::from(vec![1u8; 19])),
+                StyksBlockySupplerError::InvalidSignerAddress,
+            ),
+            (|c| c.timestamp_tolerance = 0, StyksBlockySupplerError::TimestampToleranceIsZero),
+            (
+                |c| c.expectations = vec![expectation("CSPRUSD"), expectation("CSPRUSD")],
+                StyksBlockySupplerError::ExpectationNotUnique,
+            ),
+            (|c| c.expectations = vec![expectation("BTCUSD")], StyksBlockySupplerError::PriceFeedIdNotInFeed),
+        ];
+        assert_rejected_configs(&supplier_config, cases, |config| supplier.try_set_config(config));
+
+        // The public key is not used in the signer address mode.
+        let mut config = supplier_config.clone();
+        config.public_key = Bytes::new();
+        config.signer_address = Some(Bytes::from(vec![1u8; 20]));
+        supplier.set_config(config);
+
+        // Mapped PriceFeedIds have to stay in the feed.
+        let mut feed_config = feed.get_config();
+        feed_config.price_feed_ids = vec![String::from("BTCUSD")];
+        feed.set_config(feed_config);
+        let result = supplier.try_set_config(supplier_config);
+        assert_eq!(result, Err(StyksBlockySupplerError::PriceFeedIdNotInFeed.into()));
+    }
+
+    fn expectation(price_feed_id: &str) -> StyksBlockySupplerExpectation {
+        StyksBlockySupplerExpectation {
+            price_feed_id: String::from(price_feed_id),
+            market: String::from("Gate"),
+            coin_id: String::from("CSPR"),
+            currency: String::from("USD"),
+            min_price: 1,
+            max_price: 10000,
+        }
+    }
 }
@@ -0,0 +1,437 @@
+use odra::{casper_types::{bytesrepr::Bytes, U256}, prelude::*};
+use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
+use styks_blocky_parser::{
+    attested_data::{AttestedDataError, AttestedDataOutputValue, AttestedValue},
+    blocky_claims::{BlockyClaims, BlockyClaimsError},
+    verify::VerificationError,
+};
+
+use crate::attestation::{self, AttestationError};
+
+pub type DataFeedId = String;
+
+// --- Errors ---
+
+#[odra::odra_error]
+pub enum StyksDataFeedError {
+    // Config errors.
+    ConfigNotSet = 49000,
+    DataFeedNotFound = 49001,
+    WasmHashIsEmpty = 49002,
+    WasmHashNotHex = 49003,
+    TimestampToleranceIsZero = 49004,
+    DataFeedIdNotUnique = 49005,
+
+    // Role errors.
+    NotAdminRole = 49100,
+    NotConfigManagerRole = 49101,
+
+    // Verification errors.
+    InvalidPublicKey = 49200,
+    InvalidSignature = 49201,
+    HashingError = 49202,
+    BadSignature = 49203,
+    BadWasmHash = 49204,
+    TimestampOutOfRange = 49205,
+    StaleData = 49206,
+    InvalidRecoveryId = 49207,
+    PublicKeyRecoveryFailed = 49208,
+    InvalidSignerAddress = 49209,
+
+    // Claims errors.
+    TADataDecoding = 49300,
+    TADataInvalidLength = 49301,
+    BytesConversionError = 49302,
+    OutputJsonDecoding = 49303,
+    OutputHasNoSuccessStatus = 49304,
+    InvalidHexValue = 49305,
+    InvalidU256Value = 49306,
+}
+
+impl From<VerificationError> for StyksDataFeedError {
+    fn from(error: VerificationError) -> Self {
+        use VerificationError::*;
+        match error {
+            InvalidPublicKey => StyksDataFeedError::InvalidPublicKey,
+            InvalidSignature => StyksDataFeedError::InvalidSignature,
+            HashingError => StyksDataFeedError::HashingError,
+            BadSignature => StyksDataFeedError::BadSignature,
+            InvalidRecoveryId => StyksDataFeedError::InvalidRecoveryId,
+            PublicKeyRecoveryFailed => StyksDataFeedError::PublicKeyRecoveryFailed,
+            InvalidSignerAddress => StyksDataFeedError::InvalidSignerAddress,
+        }
+    }
+}
+
+impl From<AttestationError> for StyksDataFeedError {
+    fn from(error: AttestationError) -> Self {
+        use AttestationError::*;
+        match error {
+            WasmHashIsEmpty => StyksDataFeedError::WasmHashIsEmpty,
+            WasmHashNotHex => StyksDataFeedError::WasmHashNotHex,
+            TimestampToleranceIsZero => StyksDataFeedError::TimestampToleranceIsZero,
+            BadWasmHash => StyksDataFeedError::BadWasmHash,
+            TimestampOutOfRange => StyksDataFeedError::TimestampOutOfRange,
+            Verification(error) => error.into(),
+        }
+    }
+}
+
+impl From<BlockyClaimsError> for StyksDataFeedError {
+    fn from(error: BlockyClaimsError) -> Self {
+        use BlockyClaimsError::*;
+        match error {
+            TADataDecoding => StyksDataFeedError::TADataDecoding,
+            TADataInvalidLength => StyksDataFeedError::TADataInvalidLength,
+            BytesConversionError => StyksDataFeedError::BytesConversionError,
+            OutputJsonDecoding => StyksDataFeedError::OutputJsonDecoding,
+            GuestProgramFailed(_) => StyksDataFeedError::OutputHasNoSuccessStatus,
+        }
+    }
+}
+
+impl From<AttestedDataError> for StyksDataFeedError {
+    fn from(error: AttestedDataError) -> Self {
+        use AttestedDataError::*;
+        match error {
+            InvalidHex => StyksDataFeedError::InvalidHexValue,
+            InvalidU256 => StyksDataFeedError::InvalidU256Value,
+        }
+    }
+}
+
+// --- Access Control Roles ---
+
+#[derive(Debug)]
+pub enum StyksDataFeedRole {
+    Admin,
+    ConfigManager,
+}
+
+impl StyksDataFeedRole {
+    pub fn role_id(&self) -> Role {
+        match self {
+            StyksDataFeedRole::Admin => DEFAULT_ADMIN_ROLE,
+            // start with 5, so it doesn't overlap with PriceFeed and BlockySupplier.
+            StyksDataFeedRole::ConfigManager => [5u8; 32],
+        }
+    }
+}
+
+// --- Configuration ---
+
+#[odra::odra_type]
+pub struct StyksDataFeedConfig {
+    pub public_key: Bytes,
+    pub data_feeds: Vec<(DataFeedId, String)>, // (data_feed_id, wasm_hash)
+    pub timestamp_tolerance: u64,
+}
+
+impl StyksDataFeedConfig {
+    pub fn validate(&self) -> Result<(), StyksDataFeedError> {
+        // Each data feed is registered once, with a valid wasm hash.
+        let mut seen_ids = BTreeSet::new();
+        for (data_feed_id, wasm_hash) in &self.data_feeds {
+            if !seen_ids.insert(data_feed_id) {
+                return Err(StyksDataFeedError::DataFeedIdNotUnique);
+            }
+            attestation::validate_wasm_hash(wasm_hash)?;
+        }
+
+        attestation::validate_public_key(self.public_key())?;
+        attestation::validate_timestamp_tolerance(self.timestamp_tolerance)?;
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn wasm_hash(&self, data_feed_id: &str) -> Option<String> {
+        self.data_feeds
+            .iter()
+            .find(|(id, _)| id == data_feed_id)
+            .map(|(_, wasm_hash)| wasm_hash.clone())
+    }
+}
+
+// --- Attested Data ---
+
+#[odra::odra_type]
+pub enum AttestedData {
+    Bytes(Bytes),
+    U256(U256),
+    String(String),
+}
+
+#[odra::odra_type]
+pub struct AttestedRecord {
+    pub value: AttestedData,
+    pub timestamp: u64,
+}
+
+// --- StyksDataFeed Contract ---
+
+// Stores arbitrary key/value results of Blocky guest programs.
+// Each data feed is a registered guest program (identified by its wasm hash).
+#[odra::module]
+pub struct StyksDataFeed {
+    access_control: SubModule<AccessControl>,
+    config: Var<StyksDataFeedConfig>,
+    records: Mapping<(DataFeedId, String), AttestedRecord>,
+}
+
+#[odra::module]
+impl StyksDataFeed {
+    pub fn init(&mut self) {
+        // Grant the admin role to the contract deployer.
+        let deployer = self.env().caller();
+        let admin_role = StyksDataFeedRole::Admin.role_id();
+        self.access_control
+            .unchecked_grant_role(&admin_role, &deployer);
+    }
+
+    delegate! {
+        to self.access_control {
+            fn has_role(&self, role: &Role, address: &Address) -> bool;
+            fn grant_role(&mut self, role: &Role, address: &Address);
+            fn revoke_role(&mut self, role: &Role, address: &Address);
+            fn get_role_admin(&self, role: &Role) -> Role;
+            fn renounce_role(&mut self, role: &Role, address: &Address);
+        }
+    }
+
+    pub fn set_config(&mut self, config: StyksDataFeedConfig) {
+        // Make sure only ConfigManager can set the config.
+        self.assert_config_manager(&self.env().caller());
+
+        // Validate the config.
+        config.validate().unwrap_or_revert(&self.env());
+
+        // Update the config.
+        self.config.set(config);
+    }
+
+    pub fn get_config(&self) -> StyksDataFeedConfig {
+        self.config
+            .get()
+            .unwrap_or_revert_with(&self.env(), StyksDataFeedError::ConfigNotSet)
+    }
+
+    pub fn get_config_or_none(&self) -> Option<StyksDataFeedConfig> {
+        self.config.get()
+    }
+
+    pub fn get_data(&self, data_feed_id: DataFeedId, key: String) -> Option<AttestedRecord> {
+        self.records.get(&(data_feed_id, key))
+    }
+
+    /// Verifies the signed data and stores the result under its key.
+    pub fn report_signed_data(
+        &mut self,
+        data_feed_id: DataFeedId,
+        signature: Bytes,
+        data: Bytes,
+    ) {
+        let config = self.get_config();
+
+        // Verify the signature.
+        attestation::check_signature(config.public_key(), None, &signature, &data)
+            .map_err(StyksDataFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Decode the data.
+        let claims = BlockyClaims::decode_fn_call_claims(&data)
+            .map_err(StyksDataFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Verify the claims were produced by the data feed's guest program.
+        let wasm_hash = config
+            .wasm_hash(&data_feed_id)
+            .unwrap_or_revert_with(&self.env(), StyksDataFeedError::DataFeedNotFound);
+        attestation::check_wasm_hash(&wasm_hash, &claims.hash_of_code())
+            .map_err(StyksDataFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Extract the output.
+        let output: AttestedDataOutputValue = claims
+            .output_as()
+            .map_err(StyksDataFeedError::from)
+            .unwrap_or_revert(&self.env());
+        let value = output
+            .parsed_value()
+            .map_err(StyksDataFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Verify the timestamp.
+        let current_time = self.env().get_block_time_secs();
+        attestation::check_timestamp(output.timestamp, current_time, config.timestamp_tolerance)
+            .map_err(StyksDataFeedError::from)
+            .unwrap_or_revert(&self.env());
+
+        // Don't overwrite newer data.
+        let key = (data_feed_id, output.key);
+        if let Some(record) = self.records.get(&key) {
+            if record.timestamp >= output.timestamp {
+                self.env().revert(StyksDataFeedError::StaleData);
+            }
+        }
+
+        let value = match value {
+            AttestedValue::Bytes(bytes) => AttestedData::Bytes(Bytes::from(bytes)),
+            AttestedValue::U256(value) => match U256::from_dec_str(&value) {
+                Ok(value) => AttestedData::U256(value),
+                Err(_) => self.env().revert(StyksDataFeedError::InvalidU256Value),
+            },
+            AttestedValue::String(value) => AttestedData::String(value),
+        };
+        self.records.set(
+            &key,
+            AttestedRecord {
+                value,
+                timestamp: output.timestamp,
+            },
+        );
+    }
+}
+
+impl StyksDataFeed {
+    fn assert_role(&self, address: &Address, role: StyksDataFeedRole) {
+        if !self.has_role(&role.role_id(), address) {
+            use StyksDataFeedError::*;
+            use StyksDataFeedRole::*;
+            let error = match role {
+                Admin => NotAdminRole,
+                ConfigManager => NotConfigManagerRole,
+            };
+            self.env().revert(error);
+        }
+    }
+
+    fn assert_config_manager(&self, address: &Address) {
+        self.assert_role(address, StyksDataFeedRole::ConfigManager);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use odra::host::{Deployer, HostEnv, NoArgs};
+    use styks_blocky_parser::test_utils::{TestAttestation, TestSigner};
+
+    use crate::attestation::{assert_rejected_configs, Breaker};
+
+    use super::*;
+
+    const WASM_HASH: &str = "da7afeedda7afeed";
+
+    fn attestation(key: &str, value_type: &str, value: &str, timestamp: u64) -> TestAttestation {
+        let value = format!(
+            r#"{{"key":"{}","type":"{}","value":"{}","timestamp":{}}}"#,
+            key, value_type, value, timestamp
+        );
+        TestAttestation::new("dataFunc", &value).with_hash_of_code(WASM_HASH)
+    }
+
+    fn sign(signer: &TestSigner, attestation: TestAttestation) -> (Bytes, Bytes) {
+        let (signature, data) = attestation.sign(signer);
+        (Bytes::from(signature), Bytes::from(data))
+    }
+
+    fn setup() -> (HostEnv, StyksDataFeedHostRef) {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let mut contract = StyksDataFeed::deploy(&env, NoArgs);
+        let public_key = TestSigner::from_seed(7).public_key_bytes();
+        let config = StyksDataFeedConfig {
+            public_key: Bytes::from(public_key),
+            data_feeds: vec![(String::from("ANSWERS"), String::from(WASM_HASH))],
+            timestamp_tolerance: 10,
+        };
+        contract.grant_role(&StyksDataFeedRole::ConfigManager.role_id(), &admin);
+        contract.set_config(config);
+        env.advance_block_time(1000 * 1000);
+        (env, contract)
+    }
+
+    #[test]
+    fn test_report_signed_data() {
+        let (_env, mut contract) = setup();
+        let feed_id = String::from("ANSWERS");
+        let signer = TestSigner::from_seed(7);
+
+        // Store values of each type.
+        let cases = [
+            ("bytes", "0xcafe", AttestedData::Bytes(Bytes::from(vec![0xca, 0xfe]))),
+            ("u256", "123456789", AttestedData::U256(U256::from(123456789u64))),
+            ("string", "hello", AttestedData::String(String::from("hello"))),
+        ];
+        for (value_type, value, expected) in cases {
+            let (signature, data) = sign(&signer, attestation(value_type, value_type, value, 1000));
+            contract.report_signed_data(feed_id.clone(), signature, data);
+            let record = contract.get_data(feed_id.clone(), String::from(value_type)).unwrap();
+            assert_eq!(record.value, expected);
+            assert_eq!(record.timestamp, 1000);
+        }
+
+        // Unknown keys.
+        assert_eq!(contract.get_data(feed_id, String::from("unknown")), None);
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let (_env, mut contract) = setup();
+        let config = contract.get_config();
+        let cases: Vec<(Breaker<StyksDataFeedConfig>, StyksDataFeedError)> = vec![
+            (|c| c.data_feeds[0].1 = String::new(), StyksDataFeedError::WasmHashIsEmpty),
+            (|c| c.data_feeds[0].1 = String::from("xyz0"), StyksDataFeedError::WasmHashNotHex),
+            (|c| c.public_key = Bytes::from(vec![4u8; 65]), StyksDataFeedError::InvalidPublicKey),
+            (|c| c.timestamp_tolerance = 0, StyksDataFeedError::TimestampToleranceIsZero),
+            (
+                |c| c.data_feeds.push((String::from("ANSWERS"), String::from("0123"))),
+                StyksDataFeedError::DataFeedIdNotUnique,
+            ),
+        ];
+        assert_rejected_configs(&config, cases, |config| contract.try_set_config(config));
+    }
+
+    #[test]
+    fn test_report_errors() {
+        let (_env, mut contract) = setup();
+        let feed_id = String::from("ANSWERS");
+        let signer = TestSigner::from_seed(7);
+
+        // Unknown data feed.
+        let (signature, data) = sign(&signer, attestation("a", "string", "x", 1000));
+        let result = contract.try_report_signed_data(String::from("OTHER"), signature, data);
+        assert_eq!(result, Err(StyksDataFeedError::DataFeedNotFound.into()));
+
+        // Wrong guest program.
+        let other_program = attestation("a", "string", "x", 1000).with_hash_of_code("other_hash");
+        let (signature, data) = sign(&signer, other_program);
+        let result = contract.try_report_signed_data(feed_id.clone(), signature, data);
+        assert_eq!(result, Err(StyksDataFeedError::BadWasmHash.into()));
+
+        // Wrong signer.
+        let other_signer = TestSigner::from_seed(8);
+        let (signature, data) = sign(&other_signer, attestation("a", "string", "x", 1000));
+        let result = contract.try_report_signed_data(feed_id.clone(), signature, data);
+        assert_eq!(result, Err(StyksDataFeedError::BadSignature.into()));
+
+        // Invalid values.
+        let (signature, data) = sign(&signer, attestation("a", "u256", "-1", 1000));
+        let result = contract.try_report_signed_data(feed_id.clone(), signature, data);
+        assert_eq!(result, Err(StyksDataFeedError::InvalidU256Value.into()));
+
+        // Timestamp out of range.
+        let (signature, data) = sign(&signer, attestation("a", "string", "x", 1011));
+        let result = contract.try_report_signed_data(feed_id.clone(), signature, data);
+        assert_eq!(result, Err(StyksDataFeedError::TimestampOutOfRange.into()));
+
+        // Older data doesn't overwrite newer one.
+        let (signature, data) = sign(&signer, attestation("a", "string", "new", 1005));
+        contract.report_signed_data(feed_id.clone(), signature, data);
+        let (signature, data) = sign(&signer, attestation("a", "string", "old", 1000));
+        let result = contract.try_report_signed_data(feed_id.clone(), signature, data);
+        assert_eq!(result, Err(StyksDataFeedError::StaleData.into()));
+    }
+}
@@ -0,0 +1,549 @@
+use odra::{casper_types::U256, prelude::*, ContractRef};
+use odra_modules::cep18_token::Cep18ContractRef;
+
+use crate::styks_admin_action::{StyksAdminAction, StyksContracts};
+
+const BPS_DENOMINATOR: u32 = 10_000;
+
+// --- Errors ---
+
+#[odra::odra_error]
+pub enum StyksGovernanceError {
+    // Config errors.
+    ConfigNotSet = 47000,
+    VotingPeriodCannotBeZero = 47001,
+    QuorumCannotBeZero = 47002,
+    ThresholdTooHigh = 47003,
+
+    // Proposal errors.
+    ProposalNotFound = 47100,
+    ProposalHasNoActions = 47101,
+    NotTokenHolder = 47102,
+
+    // Voting errors.
+    VotingClosed = 47200,
+    VotingStillOpen = 47201,
+    ZeroVotes = 47202,
+    AlreadyVoted = 47203,
+    NothingToWithdraw = 47204,
+
+    // Execution errors.
+    ProposalNotSucceeded = 47300,
+    TimelockNotExpired = 47301,
+    ProposalAlreadyExecuted = 47302,
+}
+
+// --- Configuration ---
+
+#[odra::odra_type]
+pub struct StyksGovernanceConfig {
+    // Duration of the voting in seconds.
+    pub voting_period: u64,
+    // Delay between the end of a successful voting and the execution.
+    pub timelock: u64,
+    // Minimal amount of tokens that must take part in the voting.
+    pub quorum: U256,
+    // Share of `for` votes (in basis points) that must be exceeded.
+    pub threshold_bps: u32,
+}
+
+impl StyksGovernanceConfig {
+    pub fn validate(&self) -> Result<(), StyksGovernanceError> {
+        if self.voting_period == 0 {
+            return Err(StyksGovernanceError::VotingPeriodCannotBeZero);
+        }
+        if self.quorum.is_zero() {
+            return Err(StyksGovernanceError::QuorumCannotBeZero);
+        }
+        if self.threshold_bps >= BPS_DENOMINATOR {
+            return Err(StyksGovernanceError::ThresholdTooHigh);
+        }
+        Ok(())
+    }
+}
+
+// --- Proposals ---
+
+#[odra::odra_type]
+pub enum ProposalState {
+    Active,
+    Defeated,
+    Queued,
+    Executable,
+    Executed,
+}
+
+#[odra::odra_type]
+pub struct Proposal {
+    pub proposer: Address,
+    pub actions: Vec<StyksAdminAction>,
+    pub voting_end: u64,
+    pub votes_for: U256,
+    pub votes_against: U256,
+    pub executed: bool,
+}
+
+#[odra::odra_type]
+pub struct Vote {
+    pub support: bool,
+    pub amount: U256,
+}
+
+// --- StyksGovernance Contract ---
+
+// Token-based DAO that holds the admin roles of the Styks contracts.
+// Votes are made by locking tokens in the contract until the voting ends.
+// The config can't be changed. To migrate, pass the admin roles to a new
+// governance contract with a proposal.
+#[odra::module]
+pub struct StyksGovernance {
+    token: Var<Address>,
+    contracts: Var<StyksContracts>,
+    config: Var<StyksGovernanceConfig>,
+    proposals: Mapping<u32, Proposal>,
+    proposals_count: Var<u32>,
+    votes: Mapping<(u32, Address), Vote>,
+}
+
+#[odra::module]
+impl StyksGovernance {
+    pub fn init(
+        &mut self,
+        token: Address,
+        contracts: StyksContracts,
+        config: StyksGovernanceConfig,
+    ) {
+        config.validate().unwrap_or_revert(&self.env());
+        self.token.set(token);
+        self.contracts.set(contracts);
+        self.config.set(config);
+    }
+
+    pub fn get_config(&self) -> StyksGovernanceConfig {
+        self.config
+            .get()
+            .unwrap_or_revert_with(&self.env(), StyksGovernanceError::ConfigNotSet)
+    }
+
+    pub fn get_token(&self) -> Address {
+        self.token
+            .get()
+            .unwrap_or_revert_with(&self.env(), StyksGovernanceError::ConfigNotSet)
+    }
+
+    pub fn get_contracts(&self) -> StyksContracts {
+        self.contracts
+            .get()
+            .unwrap_or_revert_with(&self.env(), StyksGovernanceError::ConfigNotSet)
+    }
+
+    pub fn get_proposal(&self, proposal_id: u32) -> Option<Proposal> {
+        self.proposals.get(&proposal_id)
+    }
+
+    pub fn get_proposals_count(&self) -> u32 {
+        self.proposals_count.get_or_default()
+    }
+
+    pub fn get_vote(&self, proposal_id: u32, voter: &Address) -> Option<Vote> {
+        self.votes.get(&(proposal_id, *voter))
+    }
+
+    pub fn propose(&mut self, actions: Vec<StyksAdminAction>) -> u32 {
+        if actions.is_empty() {
+            self.env().revert(StyksGovernanceError::ProposalHasNoActions);
+        }
+
+        // Only token holders can create proposals.
+        let proposer = self.env().caller();
+        if self.token().balance_of(&proposer).is_zero() {
+            self.env().revert(StyksGovernanceError::NotTokenHolder);
+        }
+
+        let config = self.get_config();
+        let proposal = Proposal {
+            proposer,
+            actions,
+            voting_end: self.env().get_block_time_secs() + config.voting_period,
+            votes_for: U256::zero(),
+            votes_against: U256::zero(),
+            executed: false,
+        };
+
+        let proposal_id = self.get_proposals_count();
+        self.proposals.set(&proposal_id, proposal);
+        self.proposals_count.set(proposal_id + 1);
+        proposal_id
+    }
+
+    /// Locks `amount` of tokens as votes. Requires the allowance for this contract.
+    pub fn vote(&mut self, proposal_id: u32, support: bool, amount: U256) {
+        let mut proposal = self.proposal(proposal_id);
+        if self.env().get_block_time_secs() > proposal.voting_end {
+            self.env().revert(StyksGovernanceError::VotingClosed);
+        }
+        if amount.is_zero() {
+            self.env().revert(StyksGovernanceError::ZeroVotes);
+        }
+
+        let voter = self.env().caller();
+        if self.votes.get(&(proposal_id, voter)).is_some() {
+            self.env().revert(StyksGovernanceError::AlreadyVoted);
+        }
+
+        // Lock the tokens.
+        let self_address = self.env().self_address();
+        self.token().transfer_from(&voter, &self_address, &amount);
+
+        if support {
+            proposal.votes_for += amount;
+        } else {
+            proposal.votes_against += amount;
+        }
+        self.proposals.set(&proposal_id, proposal);
+        self.votes.set(&(proposal_id, voter), Vote { support, amount });
+    }
+
+    /// Returns the locked tokens once the voting is over.
+    pub fn withdraw_votes(&mut self, proposal_id: u32) {
+        let proposal = self.proposal(proposal_id);
+        if self.env().get_block_time_secs() <= proposal.voting_end {
+            self.env().revert(StyksGovernanceError::VotingStillOpen);
+        }
+
+        let voter = self.env().caller();
+        let mut vote = match self.votes.get(&(proposal_id, voter)) {
+            Some(vote) if !vote.amount.is_zero() => vote,
+            _ => self.env().revert(StyksGovernanceError::NothingToWithdraw),
+        };
+
+        let amount = vote.amount;
+        vote.amount = U256::zero();
+        self.votes.set(&(proposal_id, voter), vote);
+        self.token().transfer(&voter, &amount);
+    }
+
+    pub fn execute(&mut self, proposal_id: u32) {
+        let mut proposal = self.proposal(proposal_id);
+        match self.proposal_state(proposal_id) {
+            ProposalState::Executable => {}
+            ProposalState::Queued => self.env().revert(StyksGovernanceError::TimelockNotExpired),
+            ProposalState::Executed => {
+                self.env().revert(StyksGovernanceError::ProposalAlreadyExecuted)
+            }
+            ProposalState::Active | ProposalState::Defeated => {
+                self.env().revert(StyksGovernanceError::ProposalNotSucceeded)
+            }
+        }
+
+        // Mark as executed before the external calls.
+        proposal.executed = true;
+        self.proposals.set(&proposal_id, proposal.clone());
+
+        let contracts = self.get_contracts();
+        for action in proposal.actions.iter() {
+            action.execute(self.env(), &contracts);
+        }
+    }
+
+    pub fn proposal_state(&self, proposal_id: u32) -> ProposalState {
+        let proposal = self.proposal(proposal_id);
+        let config = self.get_config();
+        let now = self.env().get_block_time_secs();
+
+        if proposal.executed {
+            return ProposalState::Executed;
+        }
+        if now <= proposal.voting_end {
+            return ProposalState::Active;
+        }
+
+        let total = proposal.votes_for + proposal.votes_against;
+        let required_for = total * U256::from(config.threshold_bps) / U256::from(BPS_DENOMINATOR);
+        if total < config.quorum || proposal.votes_for <= required_for {
+            return ProposalState::Defeated;
+        }
+
+        if now < proposal.voting_end + config.timelock {
+            ProposalState::Queued
+        } else {
+            ProposalState::Executable
+        }
+    }
+}
+
+impl StyksGovernance {
+    fn token(&self) -> Cep18ContractRef {
+        Cep18ContractRef::new(self.env(), self.get_token())
+    }
+
+    fn proposal(&self, proposal_id: u32) -> Proposal {
+        self.proposals
+            .get(&proposal_id)
+            .unwrap_or_revert_with(&self.env(), StyksGovernanceError::ProposalNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use odra::host::{Deployer, HostEnv, NoArgs};
+    use odra_modules::{access::DEFAULT_ADMIN_ROLE, cep18_token::{Cep18, Cep18HostRef, Cep18InitArgs}};
+
+    use odra::casper_types::bytesrepr::Bytes;
+    use styks_blocky_parser::test_utils::TestSigner;
+    use styks_core::SourceKey;
+
+    use crate::{
+        styks_admin_action::StyksContract,
+        styks_blocky_supplier::{
+            StyksBlockySupplerConfig, StyksBlockySupplerRole, StyksBlockySupplier,
+            StyksBlockySupplierHostRef,
+        },
+        styks_price_feed::{StyksPriceFeed, StyksPriceFeedConfig, StyksPriceFeedHostRef, StyksPriceFeedRole},
+    };
+
+    use super::*;
+
+    const VOTING_PERIOD: u64 = 1000;
+    const TIMELOCK: u64 = 500;
+
+    fn setup() -> (
+        HostEnv,
+        Cep18HostRef,
+        StyksPriceFeedHostRef,
+        StyksBlockySupplierHostRef,
+        StyksGovernanceHostRef,
+    ) {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+
+        // Deploy the governance token and share it between holders.
+        let mut token = Cep18::deploy(
+            &env,
+            Cep18InitArgs {
+                symbol: String::from("STYKS"),
+                name: String::from("Styks"),
+                decimals: 0,
+                initial_supply: U256::from(1000),
+            },
+        );
+        token.transfer(&env.get_account(1), &U256::from(300));
+        token.transfer(&env.get_account(2), &U256::from(200));
+
+        // Deploy the Styks contracts.
+        let mut feed = StyksPriceFeed::deploy(&env, NoArgs);
+        let mut supplier = StyksBlockySupplier::deploy(&env, NoArgs);
+
+        // Deploy the governance.
+        let governance = StyksGovernance::deploy(
+            &env,
+            StyksGovernanceInitArgs {
+                token: token.address(),
+                contracts: StyksContracts {
+                    price_feed: feed.address(),
+                    blocky_supplier: supplier.address(),
+                },
+                config: StyksGovernanceConfig {
+                    voting_period: VOTING_PERIOD,
+                    timelock: TIMELOCK,
+                    quorum: U256::from(400),
+                    threshold_bps: 5000,
+                },
+            },
+        );
+
+        // Hand over the admin roles to the governance.
+        feed.grant_role(&DEFAULT_ADMIN_ROLE, &governance.address());
+        feed.renounce_role(&DEFAULT_ADMIN_ROLE, &admin);
+        supplier.grant_role(&DEFAULT_ADMIN_ROLE, &governance.address());
+        supplier.renounce_role(&DEFAULT_ADMIN_ROLE, &admin);
+
+        (env, token, feed, supplier, governance)
+    }
+
+    fn vote(
+        env: &HostEnv,
+        token: &mut Cep18HostRef,
+        governance: &mut StyksGovernanceHostRef,
+        voter: Address,
+        proposal_id: u32,
+        support: bool,
+        amount: u64,
+    ) {
+        env.set_caller(voter);
+        token.approve(&governance.address(), &U256::from(amount));
+        governance.vote(proposal_id, support, U256::from(amount));
+    }
+
+    #[test]
+    fn test_proposal_lifecycle() {
+        let (env, mut token, feed, _supplier, mut governance) = setup();
+        let (holder_0, holder_1, holder_2) = (env.get_account(0), env.get_account(1), env.get_account(2));
+        let config = StyksPriceFeedConfig {
+            heartbeat_interval: 100,
+            heartbeat_tolerance: 10,
+            twap_window: 3,
+            twap_tolerance: 1,
+            price_feed_ids: vec![String::from("CSPRUSD")],
+        };
+
+        // Accounts without tokens can't propose.
+        env.set_caller(env.get_account(5));
+        let result = governance.try_propose(vec![StyksAdminAction::SetPriceFeedConfig(config.clone())]);
+        assert_eq!(result, Err(StyksGovernanceError::NotTokenHolder.into()));
+
+        // Propose to become the ConfigManager and set the config.
+        env.set_caller(holder_1);
+        let proposal_id = governance.propose(vec![
+            StyksAdminAction::GrantRole {
+                contract: StyksContract::PriceFeed,
+                role: StyksPriceFeedRole::ConfigManager.role_id(),
+                account: governance.address(),
+            },
+            StyksAdminAction::SetPriceFeedConfig(config.clone()),
+        ]);
+        assert_eq!(governance.proposal_state(proposal_id), ProposalState::Active);
+
+        // Vote.
+        vote(&env, &mut token, &mut governance, holder_0, proposal_id, true, 400);
+        vote(&env, &mut token, &mut governance, holder_1, proposal_id, false, 300);
+        vote(&env, &mut token, &mut governance, holder_2, proposal_id, true, 100);
+        assert_eq!(token.balance_of(&governance.address()), U256::from(800));
+
+        // Voting twice is not allowed.
+        env.set_caller(holder_2);
+        token.approve(&governance.address(), &U256::from(100));
+        let result = governance.try_vote(proposal_id, true, U256::from(100));
+        assert_eq!(result, Err(StyksGovernanceError::AlreadyVoted.into()));
+
+        // Can't execute or withdraw while the voting is open.
+        let result = governance.try_execute(proposal_id);
+        assert_eq!(result, Err(StyksGovernanceError::ProposalNotSucceeded.into()));
+        let result = governance.try_withdraw_votes(proposal_id);
+        assert_eq!(result, Err(StyksGovernanceError::VotingStillOpen.into()));
+
+        // End the voting. The proposal is queued until the timelock expires.
+        env.advance_block_time((VOTING_PERIOD + 1) * 1000);
+        assert_eq!(governance.proposal_state(proposal_id), ProposalState::Queued);
+        let result = governance.try_execute(proposal_id);
+        assert_eq!(result, Err(StyksGovernanceError::TimelockNotExpired.into()));
+
+        // Voting is closed.
+        let result = governance.try_vote(proposal_id, true, U256::from(100));
+        assert_eq!(result, Err(StyksGovernanceError::VotingClosed.into()));
+
+        // Execute after the timelock.
+        env.advance_block_time(TIMELOCK * 1000);
+        assert_eq!(governance.proposal_state(proposal_id), ProposalState::Executable);
+        governance.execute(proposal_id);
+        assert_eq!(governance.proposal_state(proposal_id), ProposalState::Executed);
+        assert_eq!(feed.get_config(), config);
+        assert!(feed.has_role(&StyksPriceFeedRole::ConfigManager.role_id(), &governance.address()));
+
+        // Can't execute twice.
+        let result = governance.try_execute(proposal_id);
+        assert_eq!(result, Err(StyksGovernanceError::ProposalAlreadyExecuted.into()));
+
+        // Voters get their tokens back.
+        env.set_caller(holder_1);
+        governance.withdraw_votes(proposal_id);
+        assert_eq!(token.balance_of(&holder_1), U256::from(300));
+        let result = governance.try_withdraw_votes(proposal_id);
+        assert_eq!(result, Err(StyksGovernanceError::NothingToWithdraw.into()));
+    }
+
+    #[test]
+    fn test_feed_mappings() {
+        let (env, mut token, feed, supplier, mut governance) = setup();
+        let holder_0 = env.get_account(0);
+        let source_key = SourceKey::new("Gate", "CSPR", "USD");
+        let feed_config = StyksPriceFeedConfig {
+            heartbeat_interval: 100,
+            heartbeat_tolerance: 10,
+            twap_window: 3,
+            twap_tolerance: 1,
+            price_feed_ids: vec![String::from("CSPRUSD")],
+        };
+        let supplier_config = StyksBlockySupplerConfig {
+            wasm_hash: String::from("abcd"),
+            public_key: Bytes::from(TestSigner::from_seed(1).public_key_bytes()),
+            price_feed_address: feed.address(),
+            timestamp_tolerance: 10,
+            quorum: None,
+            signer_address: None,
+            expectations: vec![],
+        };
+
+        // Configure both contracts and map the source in one proposal.
+        env.set_caller(holder_0);
+        let added = governance.propose(vec![
+            StyksAdminAction::GrantRole {
+                contract: StyksContract::PriceFeed,
+                role: StyksPriceFeedRole::ConfigManager.role_id(),
+                account: governance.address(),
+            },
+            StyksAdminAction::GrantRole {
+                contract: StyksContract::BlockySupplier,
+                role: StyksBlockySupplerRole::ConfigManager.role_id(),
+                account: governance.address(),
+            },
+            StyksAdminAction::SetPriceFeedConfig(feed_config),
+            StyksAdminAction::SetBlockySupplierConfig(supplier_config),
+            StyksAdminAction::AddFeedMapping {
+                source_key: source_key.clone(),
+                price_feed_id: String::from("CSPRUSD"),
+            },
+        ]);
+        vote(&env, &mut token, &mut governance, holder_0, added, true, 250);
+
+        // Remove it in the next one.
+        env.set_caller(holder_0);
+        let removed = governance.propose(vec![StyksAdminAction::RemoveFeedMapping {
+            source_key: source_key.clone(),
+        }]);
+        vote(&env, &mut token, &mut governance, holder_0, removed, true, 250);
+        let holder_1 = env.get_account(1);
+        vote(&env, &mut token, &mut governance, holder_1, added, true, 150);
+        vote(&env, &mut token, &mut governance, holder_1, removed, true, 150);
+
+        env.advance_block_time((VOTING_PERIOD + TIMELOCK + 1) * 1000);
+        governance.execute(added);
+        assert_eq!(supplier.get_price_feed_id(source_key.clone()), Some(String::from("CSPRUSD")));
+        governance.execute(removed);
+        assert_eq!(supplier.get_price_feed_id(source_key), None);
+    }
+
+    #[test]
+    fn test_defeated_proposal() {
+        let (env, mut token, feed, _supplier, mut governance) = setup();
+        let (holder_0, holder_1, holder_2) = (env.get_account(0), env.get_account(1), env.get_account(2));
+        let action = StyksAdminAction::RevokeRole {
+            contract: StyksContract::PriceFeed,
+            role: DEFAULT_ADMIN_ROLE,
+            account: governance.address(),
+        };
+
+        // Not enough votes to reach the quorum.
+        env.set_caller(holder_0);
+        let no_quorum = governance.propose(vec![action.clone()]);
+        vote(&env, &mut token, &mut governance, holder_2, no_quorum, true, 200);
+
+        // Not enough `for` votes.
+        env.set_caller(holder_0);
+        let rejected = governance.propose(vec![action]);
+        vote(&env, &mut token, &mut governance, holder_0, rejected, true, 300);
+        vote(&env, &mut token, &mut governance, holder_1, rejected, false, 300);
+
+        env.advance_block_time((VOTING_PERIOD + TIMELOCK + 1) * 1000);
+        for proposal_id in [no_quorum, rejected] {
+            assert_eq!(governance.proposal_state(proposal_id), ProposalState::Defeated);
+            let result = governance.try_execute(proposal_id);
+            assert_eq!(result, Err(StyksGovernanceError::ProposalNotSucceeded.into()));
+        }
+        assert!(feed.has_role(&DEFAULT_ADMIN_ROLE, &governance.address()));
+
+        // Unknown proposals.
+        let result = governance.try_execute(2);
+        assert_eq!(result, Err(StyksGovernanceError::ProposalNotFound.into()));
+    }
+}
@@ -0,0 +1,405 @@
+use odra::prelude::*;
+
+use crate::styks_admin_action::{StyksAdminAction, StyksContracts};
+
+// --- Errors ---
+
+#[odra::odra_error]
+pub enum StyksMultisigError {
+    // Config errors.
+    ConfigNotSet = 48000,
+    NoSigners = 48001,
+    DuplicatedSigner = 48002,
+    ThresholdCannotBeZero = 48003,
+    ThresholdTooHigh = 48004,
+
+    // Role errors.
+    NotSigner = 48100,
+
+    // Action errors.
+    ActionNotFound = 48200,
+    ActionAlreadyApproved = 48201,
+    ActionAlreadyExecuted = 48202,
+}
+
+// --- Actions ---
+
+#[odra::odra_type]
+pub enum StyksMultisigAction {
+    Admin(StyksAdminAction),
+    SetSigners { signers: Vec<Address>, threshold: u32 },
+}
+
+#[odra::odra_type]
+pub struct PendingAction {
+    pub action: StyksMultisigAction,
+    pub approvals: Vec<Address>,
+    pub executed: bool,
+}
+
+// --- StyksMultisig Contract ---
+
+// M-of-N multisig that holds the Admin and ConfigManager roles of the Styks
+// contracts. An action is executed with the approval that meets the threshold.
+// Only approvals of the current signers are counted.
+#[odra::module]
+pub struct StyksMultisig {
+    signers: Var<Vec<Address>>,
+    threshold: Var<u32>,
+    contracts: Var<StyksContracts>,
+    actions: Mapping<u32, PendingAction>,
+    actions_count: Var<u32>,
+}
+
+#[odra::module]
+impl StyksMultisig {
+    pub fn init(&mut self, signers: Vec<Address>, threshold: u32, contracts: StyksContracts) {
+        self.set_signers(signers, threshold);
+        self.contracts.set(contracts);
+    }
+
+    pub fn get_signers(&self) -> Vec<Address> {
+        self.signers.get_or_default()
+    }
+
+    pub fn get_threshold(&self) -> u32 {
+        self.threshold.get_or_default()
+    }
+
+    pub fn get_contracts(&self) -> StyksContracts {
+        self.contracts
+            .get()
+            .unwrap_or_revert_with(&self.env(), StyksMultisigError::ConfigNotSet)
+    }
+
+    pub fn get_action(&self, action_id: u32) -> Option<PendingAction> {
+        self.actions.get(&action_id)
+    }
+
+    pub fn get_actions_count(&self) -> u32 {
+        self.actions_count.get_or_default()
+    }
+
+    /// Creates a new action approved by the caller.
+    pub fn propose(&mut self, action: StyksMultisigAction) -> u32 {
+        let signer = self.env().caller();
+        self.assert_signer(&signer);
+
+        let action_id = self.get_actions_count();
+        self.actions_count.set(action_id + 1);
+        self.actions.set(
+            &action_id,
+            PendingAction {
+                action,
+                approvals: Vec::new(),
+                executed: false,
+            },
+        );
+        self.approve(action_id);
+        action_id
+    }
+
+    /// Approves the action and executes it if the threshold is met.
+    pub fn approve(&mut self, action_id: u32) {
+        let signer = self.env().caller();
+        self.assert_signer(&signer);
+
+        let mut pending = self
+            .actions
+            .get(&action_id)
+            .unwrap_or_revert_with(&self.env(), StyksMultisigError::ActionNotFound);
+        if pending.executed {
+            self.env().revert(StyksMultisigError::ActionAlreadyExecuted);
+        }
+        if pending.approvals.contains(&signer) {
+            self.env().revert(StyksMultisigError::ActionAlreadyApproved);
+        }
+        pending.approvals.push(signer);
+
+        // Count approvals of the current signers only.
+        let signers = self.get_signers();
+        let approvals = pending
+            .approvals
+            .iter()
+            .filter(|approval| signers.contains(approval))
+            .count();
+        if approvals >= self.get_threshold() as usize {
+            pending.executed = true;
+            self.actions.set(&action_id, pending.clone());
+            self.execute(pending.action);
+        } else {
+            self.actions.set(&action_id, pending);
+        }
+    }
+}
+
+impl StyksMultisig {
+    fn execute(&mut self, action: StyksMultisigAction) {
+        match action {
+            StyksMultisigAction::Admin(action) => {
+                action.execute(self.env(), &self.get_contracts());
+            }
+            StyksMultisigAction::SetSigners { signers, threshold } => {
+                self.set_signers(signers, threshold);
+            }
+        }
+    }
+
+    fn set_signers(&mut self, signers: Vec<Address>, threshold: u32) {
+        if signers.is_empty() {
+            self.env().revert(StyksMultisigError::NoSigners);
+        }
+        let mut unique = signers.clone();
+        unique.sort();
+        unique.dedup();
+        if unique.len() != signers.len() {
+            self.env().revert(StyksMultisigError::DuplicatedSigner);
+        }
+        if threshold == 0 {
+            self.env().revert(StyksMultisigError::ThresholdCannotBeZero);
+        }
+        if threshold as usize > signers.len() {
+            self.env().revert(StyksMultisigError::ThresholdTooHigh);
+        }
+        self.signers.set(signers);
+        self.threshold.set(threshold);
+    }
+
+    fn assert_signer(&self, address: &Address) {
+        if !self.get_signers().contains(address) {
+            self.env().revert(StyksMultisigError::NotSigner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use odra::{casper_types::bytesrepr::Bytes, host::{Deployer, HostEnv, NoArgs}};
+    use odra_modules::access::DEFAULT_ADMIN_ROLE;
+    use styks_blocky_parser::test_utils::TestSigner;
+    use styks_core::SourceKey;
+
+    use crate::{
+        styks_admin_action::StyksContract,
+        styks_blocky_supplier::{
+            StyksBlockySupplerConfig, StyksBlockySupplerRole, StyksBlockySupplier,
+            StyksBlockySupplierHostRef,
+        },
+        styks_price_feed::{StyksPriceFeed, StyksPriceFeedConfig, StyksPriceFeedHostRef, StyksPriceFeedRole},
+    };
+
+    use super::*;
+
+    fn setup() -> (HostEnv, StyksPriceFeedHostRef, StyksBlockySupplierHostRef, StyksMultisigHostRef) {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+
+        let mut feed = StyksPriceFeed::deploy(&env, NoArgs);
+        let mut supplier = StyksBlockySupplier::deploy(&env, NoArgs);
+
+        // 2-of-3 multisig.
+        let multisig = StyksMultisig::deploy(
+            &env,
+            StyksMultisigInitArgs {
+                signers: vec![env.get_account(1), env.get_account(2), env.get_account(3)],
+                threshold: 2,
+                contracts: StyksContracts {
+                    price_feed: feed.address(),
+                    blocky_supplier: supplier.address(),
+                },
+            },
+        );
+
+        // Hand over the Admin and ConfigManager roles to the multisig.
+        let config_manager = StyksPriceFeedRole::ConfigManager.role_id();
+        feed.grant_role(&config_manager, &multisig.address());
+        feed.grant_role(&DEFAULT_ADMIN_ROLE, &multisig.address());
+        feed.renounce_role(&DEFAULT_ADMIN_ROLE, &admin);
+        let config_manager = StyksBlockySupplerRole::ConfigManager.role_id();
+        supplier.grant_role(&config_manager, &multisig.address());
+        supplier.grant_role(&DEFAULT_ADMIN_ROLE, &multisig.address());
+        supplier.renounce_role(&DEFAULT_ADMIN_ROLE, &admin);
+
+        (env, feed, supplier, multisig)
+    }
+
+    #[test]
+    fn test_config_update() {
+        let (env, feed, _supplier, mut multisig) = setup();
+        let config = StyksPriceFeedConfig {
+            heartbeat_interval: 100,
+            heartbeat_tolerance: 10,
+            twap_window: 3,
+            twap_tolerance: 1,
+            price_feed_ids: vec![String::from("CSPRUSD")],
+        };
+        let action = StyksMultisigAction::Admin(StyksAdminAction::SetPriceFeedConfig(config.clone()));
+
+        // Only signers can propose.
+        env.set_caller(env.get_account(0));
+        let result = multisig.try_propose(action.clone());
+        assert_eq!(result, Err(StyksMultisigError::NotSigner.into()));
+
+        // The first approval comes with the proposal.
+        env.set_caller(env.get_account(1));
+        let action_id = multisig.propose(action);
+        assert!(feed.get_config_or_none().is_none());
+        let result = multisig.try_approve(action_id);
+        assert_eq!(result, Err(StyksMultisigError::ActionAlreadyApproved.into()));
+
+        // The second approval executes the action.
+        env.set_caller(env.get_account(2));
+        multisig.approve(action_id);
+        assert_eq!(feed.get_config(), config);
+        assert!(multisig.get_action(action_id).unwrap().executed);
+
+        // Executed actions can't be approved.
+        env.set_caller(env.get_account(3));
+        let result = multisig.try_approve(action_id);
+        assert_eq!(result, Err(StyksMultisigError::ActionAlreadyExecuted.into()));
+
+        // Unknown actions.
+        let result = multisig.try_approve(action_id + 1);
+        assert_eq!(result, Err(StyksMultisigError::ActionNotFound.into()));
+    }
+
+    #[test]
+    fn test_role_management() {
+        let (env, feed, supplier, mut multisig) = setup();
+        let producer = env.get_account(5);
+        let supplier_role = StyksPriceFeedRole::PriceSupplier.role_id();
+        let producer_role = StyksBlockySupplerRole::PriceProducer.role_id();
+
+        // Grant roles in both contracts.
+        for (contract, role) in [
+            (StyksContract::PriceFeed, supplier_role),
+            (StyksContract::BlockySupplier, producer_role),
+        ] {
+            env.set_caller(env.get_account(1));
+            let action_id = multisig.propose(StyksMultisigAction::Admin(StyksAdminAction::GrantRole {
+                contract,
+                role,
+                account: producer,
+            }));
+            env.set_caller(env.get_account(3));
+            multisig.approve(action_id);
+        }
+        assert!(feed.has_role(&supplier_role, &producer));
+        assert!(supplier.has_role(&producer_role, &producer));
+
+        // Revoke it.
+        env.set_caller(env.get_account(2));
+        let action_id = multisig.propose(StyksMultisigAction::Admin(StyksAdminAction::RevokeRole {
+            contract: StyksContract::PriceFeed,
+            role: supplier_role,
+            account: producer,
+        }));
+        env.set_caller(env.get_account(1));
+        multisig.approve(action_id);
+        assert!(!feed.has_role(&supplier_role, &producer));
+    }
+
+    #[test]
+    fn test_feed_mappings() {
+        let (env, feed, supplier, mut multisig) = setup();
+        let source_key = SourceKey::new("Gate", "CSPR", "USD");
+        let feed_config = StyksPriceFeedConfig {
+            heartbeat_interval: 100,
+            heartbeat_tolerance: 10,
+            twap_window: 3,
+            twap_tolerance: 1,
+            price_feed_ids: vec![String::from("CSPRUSD")],
+        };
+        let supplier_config = StyksBlockySupplerConfig {
+            wasm_hash: String::from("abcd"),
+            public_key: Bytes::from(TestSigner::from_seed(1).public_key_bytes()),
+            price_feed_address: feed.address(),
+            timestamp_tolerance: 10,
+            quorum: None,
+            signer_address: None,
+            expectations: vec![],
+        };
+
+        // Configure both contracts and map the source.
+        for action in [
+            StyksAdminAction::SetPriceFeedConfig(feed_config),
+            StyksAdminAction::SetBlockySupplierConfig(supplier_config),
+            StyksAdminAction::AddFeedMapping {
+                source_key: source_key.clone(),
+                price_feed_id: String::from("CSPRUSD"),
+            },
+        ] {
+            env.set_caller(env.get_account(1));
+            let action_id = multisig.propose(StyksMultisigAction::Admin(action));
+            env.set_caller(env.get_account(2));
+            multisig.approve(action_id);
+        }
+        assert_eq!(supplier.get_price_feed_id(source_key.clone()), Some(String::from("CSPRUSD")));
+
+        // Remove the mapping.
+        env.set_caller(env.get_account(1));
+        let action_id = multisig.propose(StyksMultisigAction::Admin(
+            StyksAdminAction::RemoveFeedMapping { source_key: source_key.clone() },
+        ));
+        env.set_caller(env.get_account(3));
+        multisig.approve(action_id);
+        assert_eq!(supplier.get_price_feed_id(source_key), None);
+    }
+
+    #[test]
+    fn test_signers_update() {
+        let (env, _feed, _supplier, mut multisig) = setup();
+        let (signer_1, signer_2, signer_3, signer_4) = (
+            env.get_account(1),
+            env.get_account(2),
+            env.get_account(3),
+            env.get_account(4),
+        );
+
+        // Invalid signer sets are rejected on execution.
+        for (signers, threshold, error) in [
+            (vec![], 1, StyksMultisigError::NoSigners),
+            (vec![signer_1, signer_1], 1, StyksMultisigError::DuplicatedSigner),
+            (vec![signer_1], 0, StyksMultisigError::ThresholdCannotBeZero),
+            (vec![signer_1], 2, StyksMultisigError::ThresholdTooHigh),
+        ] {
+            env.set_caller(signer_1);
+            let action_id = multisig.propose(StyksMultisigAction::SetSigners { signers, threshold });
+            env.set_caller(signer_2);
+            assert_eq!(multisig.try_approve(action_id), Err(error.into()));
+        }
+
+        // Replace signer_3 with signer_4 and require 3 approvals.
+        env.set_caller(signer_1);
+        let action_id = multisig.propose(StyksMultisigAction::SetSigners {
+            signers: vec![signer_1, signer_2, signer_4],
+            threshold: 3,
+        });
+        env.set_caller(signer_2);
+        multisig.approve(action_id);
+        assert_eq!(multisig.get_signers(), vec![signer_1, signer_2, signer_4]);
+        assert_eq!(multisig.get_threshold(), 3);
+
+        // The removed signer has no power.
+        env.set_caller(signer_3);
+        let result = multisig.try_propose(StyksMultisigAction::SetSigners {
+            signers: vec![signer_3],
+            threshold: 1,
+        });
+        assert_eq!(result, Err(StyksMultisigError::NotSigner.into()));
+
+        // The new threshold applies.
+        env.set_caller(signer_4);
+        let action_id = multisig.propose(StyksMultisigAction::SetSigners {
+            signers: vec![signer_1, signer_2],
+            threshold: 1,
+        });
+        env.set_caller(signer_1);
+        multisig.approve(action_id);
+        assert_eq!(multisig.get_threshold(), 3);
+        env.set_caller(signer_2);
+        multisig.approve(action_id);
+        assert_eq!(multisig.get_signers(), vec![signer_1, signer_2]);
+        assert_eq!(multisig.get_threshold(), 1);
+    }
+}
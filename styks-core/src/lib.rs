@@ -6,7 +6,27 @@ use odra::prelude::*;
 extern crate alloc;
 
 pub mod heartbeat;
+pub mod quorum;
 pub mod twap;
 
 pub type PriceFeedId = String;
 pub type Price = u64;
+
+// Source of a price reported by the guest program. Kept as separate fields,
+// so market names with underscores can't be confused with the coin id.
+#[odra::odra_type]
+pub struct SourceKey {
+    pub market: String,
+    pub coin_id: String,
+    pub currency: String,
+}
+
+impl SourceKey {
+    pub fn new(market: &str, coin_id: &str, currency: &str) -> Self {
+        SourceKey {
+            market: String::from(market),
+            coin_id: String::from(coin_id),
+            currency: String::from(currency),
+        }
+    }
+}
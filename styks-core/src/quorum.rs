@@ -0,0 +1,115 @@
+use odra::prelude::*;
+
+const BPS_DENOMINATOR: u64 = 10_000;
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum QuorumError {
+    ThresholdCannotBeZero,
+    ToleranceTooHigh,
+}
+
+// Decides if a set of prices reported by distinct producers agree.
+// Prices agree if they are within `tolerance_bps` basis points of the median
+// of all reported prices. At least `threshold` agreeing prices are required.
+pub struct Quorum {
+    threshold: u32,
+    tolerance_bps: u32,
+}
+
+impl Quorum {
+    pub fn new(threshold: u32, tolerance_bps: u32) -> Result<Self, QuorumError> {
+        if threshold == 0 {
+            return Err(QuorumError::ThresholdCannotBeZero);
+        }
+
+        if tolerance_bps as u64 > BPS_DENOMINATOR {
+            return Err(QuorumError::ToleranceTooHigh);
+        }
+
+        Ok(Self {
+            threshold,
+            tolerance_bps,
+        })
+    }
+
+    // Returns the median of the agreeing prices, or None if there is no quorum.
+    pub fn agreed_price(&self, prices: &[u64]) -> Option<u64> {
+        if prices.len() < self.threshold as usize {
+            return None;
+        }
+
+        let mut sorted = prices.to_vec();
+        sorted.sort_unstable();
+        let reference = median(&sorted)?;
+
+        let agreeing: Vec<u64> = sorted
+            .into_iter()
+            .filter(|price| self.is_within_tolerance(*price, reference))
+            .collect();
+
+        if agreeing.len() < self.threshold as usize {
+            return None;
+        }
+
+        median(&agreeing)
+    }
+
+    fn is_within_tolerance(&self, price: u64, reference: u64) -> bool {
+        let diff = price.abs_diff(reference) as u128;
+        let allowed = reference as u128 * self.tolerance_bps as u128;
+        diff * BPS_DENOMINATOR as u128 <= allowed
+    }
+}
+
+// Expects sorted values.
+fn median(sorted: &[u64]) -> Option<u64> {
+    let len = sorted.len();
+    if len == 0 {
+        return None;
+    }
+    let middle = len / 2;
+    if len % 2 == 1 {
+        Some(sorted[middle])
+    } else {
+        let (a, b) = (sorted[middle - 1] as u128, sorted[middle] as u128);
+        Some(((a + b) / 2) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quorum_validation() {
+        assert_eq!(Quorum::new(0, 100).err(), Some(QuorumError::ThresholdCannotBeZero));
+        assert_eq!(Quorum::new(1, 10_001).err(), Some(QuorumError::ToleranceTooHigh));
+        assert!(Quorum::new(1, 10_000).is_ok());
+    }
+
+    #[test]
+    fn test_agreed_price() {
+        // 1% tolerance, 2 agreeing prices required.
+        let quorum = Quorum::new(2, 100).unwrap();
+
+        // Not enough prices.
+        assert_eq!(quorum.agreed_price(&[]), None);
+        assert_eq!(quorum.agreed_price(&[1000]), None);
+
+        // Two agreeing prices.
+        assert_eq!(quorum.agreed_price(&[1000, 1000]), Some(1000));
+        assert_eq!(quorum.agreed_price(&[1000, 1010]), Some(1005));
+
+        // Two prices that disagree.
+        assert_eq!(quorum.agreed_price(&[1000, 1100]), None);
+
+        // Outlier is ignored.
+        assert_eq!(quorum.agreed_price(&[1000, 5000, 1004]), Some(1002));
+        assert_eq!(quorum.agreed_price(&[990, 1000, 1010, 2000]), Some(1005));
+
+        // Zero tolerance requires exact match.
+        let quorum = Quorum::new(3, 0).unwrap();
+        assert_eq!(quorum.agreed_price(&[1000, 1000, 1001]), None);
+        assert_eq!(quorum.agreed_price(&[1000, 1000, 1000, 1001]), Some(1000));
+    }
+}